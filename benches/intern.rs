@@ -0,0 +1,63 @@
+use std::thread;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use edn::{parser::parse_edn, Symbol};
+
+const NAMES: usize = 10_000;
+
+/// Interns the same names from `threads` threads at once.
+fn intern_parallel(names: &[String], threads: usize) {
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                for name in names {
+                    criterion::black_box(Symbol::from(name));
+                }
+            });
+        }
+    });
+}
+
+/// Parses one document per thread, all sharing the same keywords.
+fn parse_parallel(docs: &[String]) {
+    thread::scope(|scope| {
+        for doc in docs {
+            scope.spawn(|| criterion::black_box(parse_edn(doc).unwrap()));
+        }
+    });
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let names: Vec<String> = (0..NAMES).map(|i| format!(":bench/name-{}", i)).collect();
+    // Keep the names interned, so that only lookups are measured.
+    let _interned: Vec<Symbol> = names.iter().map(Symbol::from).collect();
+
+    let mut group = c.benchmark_group("intern");
+    for threads in [1, 2, 4, 8] {
+        group.bench_with_input(
+            BenchmarkId::new("existing", threads),
+            &threads,
+            |b, &threads| b.iter(|| intern_parallel(&names, threads)),
+        );
+    }
+    group.finish();
+
+    let doc = format!(
+        "[{}]",
+        (0..NAMES)
+            .map(|i| format!("{{:bench/id {} :bench/name x{}}}", i, i % 100))
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
+    let mut group = c.benchmark_group("parse");
+    for threads in [1, 2, 4, 8] {
+        let docs = vec![doc.clone(); threads];
+        group.bench_with_input(BenchmarkId::new("threads", threads), &docs, |b, docs| {
+            b.iter(|| parse_parallel(docs))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,362 @@
+//! The parsed and checked input of the derives.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::{Attribute, Data, DeriveInput, Fields, Ident, LitStr, Member, Result};
+
+/// The type of the keys naming struct fields.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Keys {
+    Keyword,
+    String,
+    Symbol,
+}
+
+/// Where an `#[edn(...)]` attribute is written.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Place {
+    Container,
+    Variant,
+    Field,
+}
+
+impl Place {
+    fn allows(self, name: &str) -> bool {
+        match self {
+            Place::Container => matches!(name, "ns" | "tag" | "keys" | "kebab_case" | "list"),
+            Place::Variant => matches!(name, "rename" | "tag" | "list"),
+            Place::Field => matches!(name, "rename" | "set" | "list"),
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Place::Container => "structs and enums",
+            Place::Variant => "variants",
+            Place::Field => "fields",
+        }
+    }
+}
+
+/// The options of one item, set with `#[edn(...)]`.
+#[derive(Default)]
+pub struct Attrs {
+    pub ns: Option<String>,
+    pub tag: Option<String>,
+    pub keys: Option<Keys>,
+    pub rename: Option<LitStr>,
+    pub kebab_case: bool,
+    pub set: bool,
+    pub list: bool,
+}
+
+impl Attrs {
+    fn parse(attrs: &[Attribute], place: Place) -> Result<Attrs> {
+        let mut out = Attrs::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("edn")) {
+            attr.parse_nested_meta(|meta| {
+                let name = match meta.path.get_ident() {
+                    Some(ident) => ident.to_string(),
+                    None => return Err(meta.error("expected an edn attribute name")),
+                };
+                let known = [Place::Container, Place::Variant, Place::Field]
+                    .iter()
+                    .any(|p| p.allows(&name));
+                if !known {
+                    return Err(meta.error(format!("unknown edn attribute `{}`", name)));
+                }
+                if !place.allows(&name) {
+                    return Err(meta.error(format!(
+                        "`{}` isn't supported on {}",
+                        name,
+                        place.describe()
+                    )));
+                }
+                let duplicate = match name.as_str() {
+                    "ns" => {
+                        let lit: LitStr = meta.value()?.parse()?;
+                        check(&lit, is_name, "a namespace such as `myapp.user`")?;
+                        out.ns.replace(lit.value()).is_some()
+                    }
+                    "tag" => {
+                        let lit: LitStr = meta.value()?.parse()?;
+                        check(&lit, is_tag, "a namespaced tag such as `myapp/Point`")?;
+                        out.tag.replace(lit.value()).is_some()
+                    }
+                    "rename" => {
+                        let lit: LitStr = meta.value()?.parse()?;
+                        out.rename.replace(lit).is_some()
+                    }
+                    "keys" => {
+                        let lit: LitStr = meta.value()?.parse()?;
+                        let keys = match lit.value().as_str() {
+                            "keyword" => Keys::Keyword,
+                            "string" => Keys::String,
+                            "symbol" => Keys::Symbol,
+                            _ => {
+                                return Err(syn::Error::new(
+                                    lit.span(),
+                                    "expected \"keyword\", \"string\" or \"symbol\"",
+                                ))
+                            }
+                        };
+                        out.keys.replace(keys).is_some()
+                    }
+                    "kebab_case" => std::mem::replace(&mut out.kebab_case, true),
+                    "set" => std::mem::replace(&mut out.set, true),
+                    "list" => std::mem::replace(&mut out.list, true),
+                    _ => unreachable!(),
+                };
+                if duplicate {
+                    return Err(meta.error(format!("duplicate edn attribute `{}`", name)));
+                }
+                if out.set && out.list {
+                    return Err(meta.error("a field can't be both a set and a list"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(out)
+    }
+}
+
+fn check(lit: &LitStr, valid: fn(&str) -> bool, what: &str) -> Result<()> {
+    if valid(&lit.value()) {
+        Ok(())
+    } else {
+        Err(syn::Error::new(lit.span(), format!("expected {}", what)))
+    }
+}
+
+/// Whether `s` can be the name or the namespace of a symbol.
+fn is_name(s: &str) -> bool {
+    let constituent = |c: char| c.is_ascii_alphanumeric() || ".*+!-_?$%&=<>:#".contains(c);
+    !s.is_empty()
+        && s.chars().all(constituent)
+        && !s.starts_with(|c: char| c.is_ascii_digit() || c == ':' || c == '#')
+}
+
+/// The name of a field or variant without `rename`.
+fn ident_name(ident: &Ident, container: &Attrs) -> String {
+    let name = ident.unraw().to_string();
+    if container.kebab_case {
+        kebab_case(&name)
+    } else {
+        name
+    }
+}
+
+/// Checks that an identifier without `rename` makes a valid name, which
+/// identifiers outside ASCII don't.
+fn check_ident(ident: &Ident, container: &Attrs) -> Result<()> {
+    if is_name(&ident_name(ident, container)) {
+        Ok(())
+    } else {
+        Err(syn::Error::new(
+            ident.span(),
+            "not a valid keyword or symbol name, set one with `rename`",
+        ))
+    }
+}
+
+fn is_tag(s: &str) -> bool {
+    match s.split_once('/') {
+        Some((ns, name)) => is_name(ns) && is_name(name) && ns.starts_with(char::is_alphabetic),
+        None => false,
+    }
+}
+
+pub enum Style {
+    /// `{ a: T }`
+    Named,
+    /// `(T, U)`
+    Tuple,
+    /// No fields.
+    Unit,
+}
+
+pub struct Field<'a> {
+    pub member: Member,
+    pub ident: Option<&'a Ident>,
+    pub attrs: Attrs,
+}
+
+pub struct Variant<'a> {
+    pub ident: &'a Ident,
+    pub attrs: Attrs,
+    pub style: Style,
+    pub fields: Vec<Field<'a>>,
+}
+
+pub enum Body<'a> {
+    Struct(Style, Vec<Field<'a>>),
+    Enum(Vec<Variant<'a>>),
+}
+
+pub struct Container<'a> {
+    pub attrs: Attrs,
+    pub body: Body<'a>,
+}
+
+impl<'a> Container<'a> {
+    pub fn from_ast(input: &'a DeriveInput) -> Result<Container<'a>> {
+        let attrs = Attrs::parse(&input.attrs, Place::Container)?;
+        let body = match &input.data {
+            Data::Struct(data) => {
+                let (style, fields) = fields(&data.fields, &attrs)?;
+                if attrs.list && !matches!(style, Style::Tuple) {
+                    return Err(syn::Error::new(
+                        input.ident.span(),
+                        "`list` only applies to tuple structs",
+                    ));
+                }
+                Body::Struct(style, fields)
+            }
+            Data::Enum(data) => {
+                if attrs.list {
+                    return Err(syn::Error::new(
+                        input.ident.span(),
+                        "`list` only applies to tuple structs, put it on the variants",
+                    ));
+                }
+                let container = &attrs;
+                let variants = data
+                    .variants
+                    .iter()
+                    .map(|v| {
+                        let attrs = Attrs::parse(&v.attrs, Place::Variant)?;
+                        match &attrs.rename {
+                            Some(rename) => check(rename, is_name, "a keyword name")?,
+                            None => check_ident(&v.ident, container)?,
+                        }
+                        let (style, fields) = fields(&v.fields, container)?;
+                        if attrs.list && !matches!(style, Style::Tuple) {
+                            return Err(syn::Error::new_spanned(
+                                v,
+                                "`list` only applies to tuple variants",
+                            ));
+                        }
+                        Ok(Variant {
+                            ident: &v.ident,
+                            attrs,
+                            style,
+                            fields,
+                        })
+                    })
+                    .collect::<Result<_>>()?;
+                Body::Enum(variants)
+            }
+            Data::Union(data) => {
+                return Err(syn::Error::new_spanned(
+                    data.union_token,
+                    "EDN conversions can't be derived for unions",
+                ))
+            }
+        };
+        if attrs.keys == Some(Keys::String) && attrs.ns.is_some() {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "`ns` needs keyword or symbol keys",
+            ));
+        }
+        Ok(Container { attrs, body })
+    }
+
+    /// Returns the name of a field or variant in EDN: its `rename`, or its
+    /// identifier, in kebab-case if asked for.
+    pub fn name(&self, ident: &Ident, rename: &Option<LitStr>) -> String {
+        match rename {
+            Some(name) => name.value(),
+            None => ident_name(ident, &self.attrs),
+        }
+    }
+
+    /// Returns an expression making the map key of a field.
+    pub fn key(&self, field: &Field) -> TokenStream {
+        let ident = field.ident.expect("a named field");
+        let name = self.name(ident, &field.attrs.rename);
+        let keys = self.attrs.keys.unwrap_or(Keys::Keyword);
+        let text = match &self.attrs.ns {
+            Some(ns) => format!("{}/{}", ns, name),
+            None => name,
+        };
+        match keys {
+            Keys::Keyword => quote!(::edn::value::Key::Keyword(::edn::Keyword::from(#text))),
+            Keys::String => quote!(::edn::value::Key::String(::std::string::String::from(#text))),
+            Keys::Symbol => quote!(::edn::value::Key::Symbol(::edn::Symbol::from(#text))),
+        }
+    }
+
+    /// Returns the keyword text of a variant, in the namespace of the enum.
+    pub fn variant_keyword(&self, variant: &Variant) -> String {
+        let name = self.name(variant.ident, &variant.attrs.rename);
+        match &self.attrs.ns {
+            Some(ns) => format!("{}/{}", ns, name),
+            None => name,
+        }
+    }
+}
+
+fn fields<'a>(fields: &'a Fields, container: &Attrs) -> Result<(Style, Vec<Field<'a>>)> {
+    let style = match fields {
+        Fields::Named(_) => Style::Named,
+        Fields::Unnamed(_) => Style::Tuple,
+        Fields::Unit => Style::Unit,
+    };
+    let fields = fields
+        .iter()
+        .enumerate()
+        .map(|(i, f)| {
+            let attrs = Attrs::parse(&f.attrs, Place::Field)?;
+            match &attrs.rename {
+                Some(_) if f.ident.is_none() => {
+                    return Err(syn::Error::new_spanned(
+                        f,
+                        "`rename` only applies to named fields",
+                    ))
+                }
+                Some(rename) if container.keys != Some(Keys::String) => {
+                    check(rename, is_name, "a keyword or symbol name")?
+                }
+                None if container.keys != Some(Keys::String) => {
+                    if let Some(ident) = &f.ident {
+                        check_ident(ident, container)?;
+                    }
+                }
+                _ => {}
+            }
+            Ok(Field {
+                member: match &f.ident {
+                    Some(ident) => Member::Named(ident.clone()),
+                    None => Member::Unnamed(i.into()),
+                },
+                ident: f.ident.as_ref(),
+                attrs,
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok((style, fields))
+}
+
+/// Converts `CamelCase` and `snake_case` names to `kebab-case`.
+fn kebab_case(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    let mut word = false;
+    for c in s.chars() {
+        if c == '_' {
+            out.push('-');
+            word = false;
+        } else if c.is_uppercase() {
+            if word {
+                out.push('-');
+            }
+            out.extend(c.to_lowercase());
+            word = false;
+        } else {
+            out.push(c);
+            word = c.is_alphanumeric();
+        }
+    }
+    out
+}
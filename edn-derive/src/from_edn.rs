@@ -0,0 +1,115 @@
+//! `#[derive(FromEdn)]`
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_quote, DeriveInput, Result};
+
+use crate::ast::{Body, Container, Field, Style};
+
+pub fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let cont = Container::from_ast(input)?;
+    let untag = cont
+        .attrs
+        .tag
+        .as_ref()
+        .map(|tag| quote!(let value = ::edn::__private::untag(value, #tag)?;));
+    let body = match &cont.body {
+        Body::Struct(style, fields) => {
+            construct(&cont, quote!(Self), style, fields, cont.attrs.list)
+        }
+        Body::Enum(variants) => {
+            let arms = variants.iter().map(|v| {
+                let ident = v.ident;
+                let name = match &v.attrs.tag {
+                    Some(tag) => format!("#{}", tag),
+                    None => format!(":{}", cont.variant_keyword(v)),
+                };
+                let body = match v.style {
+                    Style::Unit => quote! {
+                        ::edn::__private::nil(__data.unwrap_or(::edn::Value::Nil))?;
+                        ::std::result::Result::Ok(Self::#ident)
+                    },
+                    _ => {
+                        let construct = construct(
+                            &cont,
+                            quote!(Self::#ident),
+                            &v.style,
+                            &v.fields,
+                            v.attrs.list,
+                        );
+                        quote! {
+                            let value = ::edn::__private::data(__data, &__variant)?;
+                            #construct
+                        }
+                    }
+                };
+                quote!(#name => { #body })
+            });
+            quote! {
+                let (__variant, __data) = ::edn::__private::variant(value)?;
+                match __variant.as_str() {
+                    #(#arms)*
+                    _ => ::std::result::Result::Err(::edn::__private::unknown_variant(&__variant)),
+                }
+            }
+        }
+    };
+
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::edn::FromEdn));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let ident = &input.ident;
+    Ok(quote! {
+        impl #impl_generics ::edn::FromEdn for #ident #ty_generics #where_clause {
+            fn from_edn(value: ::edn::Value) -> ::edn::error::Result<Self> {
+                #untag
+                #body
+            }
+        }
+    })
+}
+
+/// Returns the statements building `path`, a struct or a variant, from
+/// `value`.
+fn construct(
+    cont: &Container,
+    path: TokenStream,
+    style: &Style,
+    fields: &[Field],
+    list: bool,
+) -> TokenStream {
+    match style {
+        Style::Unit => quote! {
+            ::edn::__private::nil(value)?;
+            ::std::result::Result::Ok(#path)
+        },
+        Style::Tuple if fields.len() == 1 && !list => quote! {
+            ::std::result::Result::Ok(#path(::edn::FromEdn::from_edn(value)?))
+        },
+        Style::Tuple => {
+            let len = fields.len();
+            let bindings: Vec<_> = (0..len).map(|i| format_ident!("__field{}", i)).collect();
+            quote! {
+                let [#(#bindings),*] = ::edn::__private::elements::<#len>(value)?;
+                ::std::result::Result::Ok(#path(#(::edn::FromEdn::from_edn(#bindings)?),*))
+            }
+        }
+        Style::Named if fields.is_empty() => quote! {
+            ::edn::__private::map(value)?;
+            ::std::result::Result::Ok(#path {})
+        },
+        Style::Named => {
+            let inits = fields.iter().map(|f| {
+                let member = &f.member;
+                let key = cont.key(f);
+                quote!(#member: ::edn::__private::field(&mut __map, #key)?)
+            });
+            quote! {
+                let mut __map = ::edn::__private::map(value)?;
+                ::std::result::Result::Ok(#path { #(#inits),* })
+            }
+        }
+    }
+}
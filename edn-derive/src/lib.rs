@@ -0,0 +1,121 @@
+//! Derive macros for the `ToEdn` and `FromEdn` traits of the `edn` crate,
+//! which convert Rust types to and from `edn::Value`s directly. Enable them
+//! with the `derive` feature of `edn`.
+//!
+//! Structs become maps with keyword keys, tuple structs vectors, newtype
+//! structs their field and unit structs `nil`. Unit variants of enums become
+//! keywords, `:Variant`, and variants with data a map with a single entry,
+//! `{:Variant data}`, whose data is built like a struct.
+//!
+//! ```
+//! use edn::{parser::parse_edn, FromEdn, ToEdn};
+//!
+//! #[derive(ToEdn, FromEdn, Debug, PartialEq)]
+//! #[edn(tag = "myapp/Point")]
+//! struct Point(i64, i64);
+//!
+//! #[derive(ToEdn, FromEdn, Debug, PartialEq)]
+//! #[edn(ns = "shape", kebab_case)]
+//! enum Shape {
+//!     Empty,
+//!     Polygon {
+//!         #[edn(list)]
+//!         points: Vec<Point>,
+//!         fill_color: Option<String>,
+//!     },
+//! }
+//!
+//! let shape = Shape::Polygon { points: vec![Point(0, 0), Point(1, 2)], fill_color: None };
+//! let edn = parse_edn(
+//!     "{:shape/polygon {:shape/points (#myapp/Point [0 0] #myapp/Point [1 2])
+//!                       :shape/fill-color nil}}",
+//! )
+//! .unwrap();
+//! assert_eq!(shape.to_edn(), edn);
+//! assert_eq!(Shape::from_edn(edn).unwrap(), shape);
+//! assert_eq!(Shape::Empty.to_edn(), parse_edn(":shape/empty").unwrap());
+//! ```
+//!
+//! # Attributes
+//!
+//! On structs and enums:
+//!
+//! - `#[edn(ns = "block")]` puts the keys of fields and the keywords of
+//!   variants in a namespace, `:block/uuid`.
+//! - `#[edn(keys = "string")]` names fields with strings; `"keyword"` is the
+//!   default and `"symbol"` the other choice.
+//! - `#[edn(kebab_case)]` converts the names of fields and variants to
+//!   kebab-case, `:fill-color` for `fill_color`.
+//! - `#[edn(tag = "myapp/Point")]` wraps the value in a tagged literal,
+//!   `#myapp/Point [1 2]`.
+//! - `#[edn(list)]` makes a tuple struct a list instead of a vector.
+//!
+//! On variants:
+//!
+//! - `#[edn(rename = "name")]` names the variant.
+//! - `#[edn(tag = "myapp/Circle")]` makes the variant a tagged literal of its
+//!   data, `#myapp/Circle 1.5`, instead of a map.
+//! - `#[edn(list)]` makes the data of a tuple variant a list.
+//!
+//! On fields:
+//!
+//! - `#[edn(rename = "name")]` names the field. The namespace still applies.
+//! - `#[edn(set)]` and `#[edn(list)]` make a collection a set or a list
+//!   instead of converting it with its own `ToEdn`.
+//!
+//! `FromEdn` accepts what `ToEdn` produces. Unknown keys are ignored and a
+//! missing key reads as `nil`, so `Option` fields may be left out.
+//!
+//! Mistakes in attributes are compile errors:
+//!
+//! ```compile_fail
+//! #[derive(edn::ToEdn)]
+//! #[edn(tag = "Point")] // expected a namespaced tag such as `myapp/Point`
+//! struct Point(i64, i64);
+//! ```
+//!
+//! ```compile_fail
+//! #[derive(edn::ToEdn)]
+//! struct Tags {
+//!     #[edn(set, list)] // a field can't be both a set and a list
+//!     tags: Vec<String>,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! #[derive(edn::ToEdn)]
+//! #[edn(list)] // `list` only applies to tuple structs
+//! struct User {
+//!     name: String,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! #[derive(edn::ToEdn)]
+//! struct Menu {
+//!     café: String, // not a valid keyword or symbol name, set one with `rename`
+//! }
+//! ```
+
+mod ast;
+mod from_edn;
+mod to_edn;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+#[proc_macro_derive(ToEdn, attributes(edn))]
+pub fn derive_to_edn(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    to_edn::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(FromEdn, attributes(edn))]
+pub fn derive_from_edn(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_edn::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
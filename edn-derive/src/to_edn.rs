@@ -0,0 +1,125 @@
+//! `#[derive(ToEdn)]`
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_quote, DeriveInput, Result};
+
+use crate::ast::{Body, Container, Field, Style};
+
+pub fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let cont = Container::from_ast(input)?;
+    let body = match &cont.body {
+        Body::Struct(style, fields) => {
+            let values: Vec<TokenStream> = fields
+                .iter()
+                .map(|f| {
+                    let member = &f.member;
+                    quote!(&self.#member)
+                })
+                .collect();
+            compound(&cont, style, fields, &values, cont.attrs.list)
+        }
+        Body::Enum(variants) => {
+            let arms = variants.iter().map(|v| {
+                let ident = v.ident;
+                let bindings: Vec<_> = (0..v.fields.len())
+                    .map(|i| format_ident!("__field{}", i))
+                    .collect();
+                let members = v.fields.iter().map(|f| &f.member);
+                let pat = match v.style {
+                    Style::Named => quote!(Self::#ident { #(#members: ref #bindings),* }),
+                    Style::Tuple => quote!(Self::#ident(#(ref #bindings),*)),
+                    Style::Unit => quote!(Self::#ident),
+                };
+                let values: Vec<_> = bindings.iter().map(|b| quote!(#b)).collect();
+                let data = match v.style {
+                    Style::Unit => None,
+                    _ => Some(compound(&cont, &v.style, &v.fields, &values, v.attrs.list)),
+                };
+                let keyword = cont.variant_keyword(v);
+                let value = match (&v.attrs.tag, data) {
+                    (Some(tag), data) => tagged(tag, data.unwrap_or(quote!(::edn::Value::Nil))),
+                    (None, None) => quote!(::edn::Value::Keyword(::edn::Keyword::from(#keyword))),
+                    (None, Some(data)) => quote! {
+                        ::edn::Value::Map(::std::iter::FromIterator::from_iter([(
+                            ::edn::value::Key::Keyword(::edn::Keyword::from(#keyword)),
+                            #data,
+                        )]))
+                    },
+                };
+                quote!(#pat => #value)
+            });
+            quote!(match *self { #(#arms,)* })
+        }
+    };
+    let body = match &cont.attrs.tag {
+        Some(tag) => tagged(tag, body),
+        None => body,
+    };
+
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::edn::ToEdn));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let ident = &input.ident;
+    Ok(quote! {
+        impl #impl_generics ::edn::ToEdn for #ident #ty_generics #where_clause {
+            fn to_edn(&self) -> ::edn::Value {
+                #body
+            }
+        }
+    })
+}
+
+/// Returns the value of a struct or of the data of a variant, whose fields
+/// are read with `values`. Tuples of one field are their field, unless
+/// they're a `list`.
+fn compound(
+    cont: &Container,
+    style: &Style,
+    fields: &[Field],
+    values: &[TokenStream],
+    list: bool,
+) -> TokenStream {
+    match style {
+        Style::Unit => quote!(::edn::Value::Nil),
+        Style::Tuple if fields.len() == 1 && !list => field(&fields[0], &values[0]),
+        Style::Tuple => {
+            let items = fields.iter().zip(values).map(|(f, v)| field(f, v));
+            let variant = if list { quote!(List) } else { quote!(Vector) };
+            quote! {
+                ::edn::Value::#variant(::std::iter::IntoIterator::into_iter([#(#items),*]).collect())
+            }
+        }
+        Style::Named => {
+            let entries = fields.iter().zip(values).map(|(f, v)| {
+                let key = cont.key(f);
+                let value = field(f, v);
+                quote!((#key, #value))
+            });
+            quote!(::edn::Value::Map(::std::iter::FromIterator::from_iter([#(#entries),*])))
+        }
+    }
+}
+
+fn field(field: &Field, value: &TokenStream) -> TokenStream {
+    let collection = if field.attrs.set {
+        quote!(Set)
+    } else if field.attrs.list {
+        quote!(List)
+    } else {
+        return quote!(::edn::ToEdn::to_edn(#value));
+    };
+    quote! {
+        ::edn::Value::#collection(
+            ::std::iter::IntoIterator::into_iter(#value)
+                .map(::edn::ToEdn::to_edn)
+                .collect(),
+        )
+    }
+}
+
+fn tagged(tag: &str, value: TokenStream) -> TokenStream {
+    quote!(::edn::Value::Tagged(::edn::Symbol::from(#tag), ::std::boxed::Box::new(#value)))
+}
@@ -0,0 +1,148 @@
+use edn::{error::Error, parser::parse_edn, FromEdn, ToEdn, Value};
+
+fn edn(s: &str) -> Value {
+    parse_edn(s).unwrap()
+}
+
+fn round_trip<T: ToEdn + FromEdn + PartialEq + std::fmt::Debug>(value: T, expected: &str) {
+    assert_eq!(value.to_edn(), edn(expected));
+    assert_eq!(T::from_edn(edn(expected)).unwrap(), value);
+}
+
+#[derive(ToEdn, FromEdn, Debug, PartialEq)]
+struct User {
+    name: String,
+    #[edn(rename = "e-mail")]
+    email: Option<String>,
+    #[edn(set)]
+    roles: Vec<String>,
+    #[edn(list)]
+    history: Vec<i64>,
+}
+
+#[derive(ToEdn, FromEdn, Debug, PartialEq)]
+#[edn(ns = "block", kebab_case)]
+struct Block {
+    block_id: i64,
+    r#type: String,
+    children: Vec<Block>,
+}
+
+#[derive(ToEdn, FromEdn, Debug, PartialEq)]
+#[edn(keys = "string")]
+struct Row {
+    user_id: i64,
+}
+
+#[derive(ToEdn, FromEdn, Debug, PartialEq)]
+#[edn(keys = "symbol", ns = "db")]
+struct Attr {
+    ident: edn::Keyword,
+}
+
+#[derive(ToEdn, FromEdn, Debug, PartialEq)]
+#[edn(tag = "myapp/Point")]
+struct Point(i64, i64);
+
+#[derive(ToEdn, FromEdn, Debug, PartialEq)]
+#[edn(list)]
+struct Pair(String, i64);
+
+#[derive(ToEdn, FromEdn, Debug, PartialEq)]
+struct Id(u64);
+
+#[derive(ToEdn, FromEdn, Debug, PartialEq)]
+struct Unit;
+
+#[derive(ToEdn, FromEdn, Debug, PartialEq)]
+enum Shape {
+    Empty,
+    Circle(f64),
+    #[edn(tag = "myapp/Rect")]
+    Rect {
+        w: i64,
+        h: i64,
+    },
+    #[edn(list)]
+    Line(Point, Point),
+    #[edn(rename = "tri")]
+    Triangle(Vec<i64>),
+}
+
+#[derive(ToEdn, FromEdn, Debug, PartialEq)]
+struct Wrapper<T> {
+    items: Vec<T>,
+}
+
+#[test]
+fn test_structs() {
+    round_trip(
+        User {
+            name: "rich".into(),
+            email: None,
+            roles: vec!["admin".into()],
+            history: vec![1, 2],
+        },
+        "{:name \"rich\" :e-mail nil :roles #{\"admin\"} :history (1 2)}",
+    );
+    round_trip(
+        Block {
+            block_id: 1,
+            r#type: "page".into(),
+            children: vec![],
+        },
+        "{:block/block-id 1 :block/type \"page\" :block/children []}",
+    );
+    round_trip(Row { user_id: 1 }, "{\"user_id\" 1}");
+    round_trip(
+        Attr {
+            ident: edn::Keyword::from("a"),
+        },
+        "{db/ident :a}",
+    );
+    round_trip(Point(1, 2), "#myapp/Point [1 2]");
+    round_trip(Pair("a".into(), 1), "(\"a\" 1)");
+    round_trip(Id(7), "7");
+    round_trip(Unit, "nil");
+    round_trip(Wrapper { items: vec![1, 2] }, "{:items [1 2]}");
+}
+
+#[test]
+fn test_enums() {
+    round_trip(Shape::Empty, ":Empty");
+    round_trip(Shape::Circle(1.5), "{:Circle 1.5}");
+    round_trip(Shape::Rect { w: 1, h: 2 }, "#myapp/Rect {:w 1 :h 2}");
+    round_trip(
+        Shape::Line(Point(0, 0), Point(1, 1)),
+        "{:Line (#myapp/Point [0 0] #myapp/Point [1 1])}",
+    );
+    round_trip(Shape::Triangle(vec![]), "{:tri []}");
+}
+
+#[test]
+fn test_errors() {
+    // Missing options are nil, and unknown keys are ignored.
+    assert_eq!(
+        User::from_edn(edn("{:name \"a\" :roles [] :history [] :extra 1}")).unwrap(),
+        User {
+            name: "a".into(),
+            email: None,
+            roles: vec![],
+            history: vec![],
+        }
+    );
+    let message = |s| match User::from_edn(edn(s)) {
+        Err(Error::InvalidValue(message)) => message,
+        r => panic!("{:?}", r),
+    };
+    assert_eq!(message("{:roles [] :history []}"), "missing key :name");
+    assert_eq!(
+        message("{:name 1 :roles [] :history []}"),
+        ":name: expected a string, found 1"
+    );
+    assert!(Point::from_edn(edn("[1 2]")).is_err());
+    assert!(Point::from_edn(edn("#myapp/Point [1 2 3]")).is_err());
+    assert!(Shape::from_edn(edn(":Square")).is_err());
+    assert!(Shape::from_edn(edn(":Circle")).is_err());
+    assert!(Shape::from_edn(edn("{:Empty 1}")).is_err());
+}
@@ -0,0 +1,447 @@
+//! Structural diff between two `Value`s.
+//!
+//! [`diff`] walks both trees together and reports what changed, with the key
+//! path of every change:
+//!
+//! - maps are compared key by key, and values under the same key are diffed
+//!   recursively,
+//! - sets are compared by membership,
+//! - vectors and lists are aligned on their longest common subsequence, so
+//!   insertions and removals don't show up as a change at every later index,
+//!   and elements that only changed position are reported as moves.
+//!
+//! Paths lead through the new value. Only the last index of a removed
+//! sequence element, and the origin of a move, are positions in the old
+//! sequence.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::walk::{Path, PathSegment};
+use crate::Value;
+
+/// Above this many element comparisons, sequences are compared position by
+/// position instead of being aligned.
+const MAX_ALIGN_COST: usize = 4_000_000;
+
+/// A single difference between two values.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    /// A map entry, set member or sequence element that only exists in the
+    /// new value. For set members the path is the path of the set.
+    Added { path: Path, value: Value },
+    /// A map entry, set member or sequence element that only exists in the
+    /// old value. For set members the path is the path of the set.
+    Removed { path: Path, value: Value },
+    /// A value that was replaced by a different one.
+    Changed { path: Path, old: Value, new: Value },
+    /// A sequence element that is present in both values at a different
+    /// position. The path is the path of the sequence.
+    Moved {
+        path: Path,
+        from: usize,
+        to: usize,
+        value: Value,
+    },
+}
+
+/// The differences between two values, as returned by [`diff`].
+///
+/// The `Display` impl renders one change per line:
+///
+/// ```text
+/// + [:tags] :new
+/// - [:a :b] 1
+/// ~ [:c] 1 -> 2
+/// > [:v] 0 -> 3 :x
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Diff {
+    changes: Vec<Change>,
+}
+
+impl Diff {
+    /// Returns true if the values are equal.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Returns the number of changes.
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Gets an iterator over the changes.
+    pub fn iter(&self) -> std::slice::Iter<'_, Change> {
+        self.changes.iter()
+    }
+
+    /// Returns the changes.
+    pub fn into_changes(self) -> Vec<Change> {
+        self.changes
+    }
+}
+
+impl IntoIterator for Diff {
+    type Item = Change;
+    type IntoIter = std::vec::IntoIter<Change>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Diff {
+    type Item = &'a Change;
+    type IntoIter = std::slice::Iter<'a, Change>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.iter()
+    }
+}
+
+/// Computes the differences that turn `a` into `b`.
+pub fn diff(a: &Value, b: &Value) -> Diff {
+    let mut changes = Vec::new();
+    diff_into(&mut Vec::new(), a, b, &mut changes);
+    Diff { changes }
+}
+
+fn diff_into(path: &mut Path, a: &Value, b: &Value, changes: &mut Vec<Change>) {
+    if a == b {
+        return;
+    }
+    match (a, b) {
+        (Value::Map(ma), Value::Map(mb)) => {
+            // Maps iterate in key order, so changes come out in key order.
+            for (k, va) in ma {
+                path.push(PathSegment::Key(k.clone()));
+                match mb.get(k) {
+                    Some(vb) => diff_into(path, va, vb, changes),
+                    None => changes.push(Change::Removed {
+                        path: path.clone(),
+                        value: va.clone(),
+                    }),
+                }
+                path.pop();
+            }
+            for (k, vb) in mb.iter().filter(|(k, _)| !ma.contains_key(k)) {
+                path.push(PathSegment::Key(k.clone()));
+                changes.push(Change::Added {
+                    path: path.clone(),
+                    value: vb.clone(),
+                });
+                path.pop();
+            }
+        }
+        (Value::Set(sa), Value::Set(sb)) => {
+            for v in set_difference(sa, sb) {
+                changes.push(Change::Removed {
+                    path: path.clone(),
+                    value: v.clone(),
+                });
+            }
+            for v in set_difference(sb, sa) {
+                changes.push(Change::Added {
+                    path: path.clone(),
+                    value: v.clone(),
+                });
+            }
+        }
+        (Value::Vector(va), Value::Vector(vb)) | (Value::List(va), Value::List(vb)) => {
+            let va: Vec<&Value> = va.iter().collect();
+            let vb: Vec<&Value> = vb.iter().collect();
+            diff_seq(path, &va, &vb, changes)
+        }
+        (Value::Tagged(ta, va), Value::Tagged(tb, vb)) if ta == tb => {
+            path.push(PathSegment::Tagged);
+            diff_into(path, va, vb, changes);
+            path.pop();
+        }
+        _ => changes.push(Change::Changed {
+            path: path.clone(),
+            old: a.clone(),
+            new: b.clone(),
+        }),
+    }
+}
+
+/// Returns the members of `a` that aren't in `b`, sorted so that diffs and
+/// patches of the same sets come out the same on every run.
+pub(crate) fn set_difference<'a>(a: &'a HashSet<Value>, b: &HashSet<Value>) -> Vec<&'a Value> {
+    let mut members: Vec<(String, &Value)> = a
+        .iter()
+        .filter(|v| !b.contains(*v))
+        .map(|v| (sort_key(v), v))
+        .collect();
+    members.sort_by(|(ka, _), (kb, _)| ka.cmp(kb));
+    members.into_iter().map(|(_, v)| v).collect()
+}
+
+/// The printed form of `value` with the members of sets sorted, which
+/// doesn't depend on the order of iteration.
+fn sort_key(value: &Value) -> String {
+    fn join<'a>(values: impl Iterator<Item = &'a Value>) -> String {
+        values.map(sort_key).collect::<Vec<_>>().join(" ")
+    }
+    match value {
+        Value::Vector(v) => format!("[{}]", join(v.iter())),
+        Value::List(v) => format!("({})", join(v.iter())),
+        Value::Set(s) => {
+            let mut members: Vec<String> = s.iter().map(sort_key).collect();
+            members.sort();
+            format!("#{{{}}}", members.join(" "))
+        }
+        Value::Map(m) => {
+            let entries: Vec<String> = m
+                .iter()
+                .map(|(k, v)| format!("{} {}", k, sort_key(v)))
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        Value::Tagged(t, v) => format!("#{} {}", t, sort_key(v)),
+        v => v.to_string(),
+    }
+}
+
+fn diff_seq(path: &mut Path, a: &[&Value], b: &[&Value], changes: &mut Vec<Change>) {
+    let gaps = gaps(a, b);
+
+    // Unaligned elements that exist on both sides have moved.
+    let mut inserted: HashMap<&Value, Vec<usize>> = HashMap::new();
+    for j in gaps.iter().flat_map(|(_, ins)| ins.iter()).rev() {
+        inserted.entry(b[*j]).or_default().push(*j);
+    }
+    let mut moved_to = vec![false; b.len()];
+    let mut moved_from = vec![false; a.len()];
+    for i in gaps.iter().flat_map(|(rem, _)| rem.iter()) {
+        if let Some(j) = inserted.get_mut(a[*i]).and_then(Vec::pop) {
+            moved_from[*i] = true;
+            moved_to[j] = true;
+            changes.push(Change::Moved {
+                path: path.clone(),
+                from: *i,
+                to: j,
+                value: a[*i].clone(),
+            });
+        }
+    }
+
+    // Whatever is left of a gap is a change where both sides have an element,
+    // and a removal or an insertion otherwise.
+    for (removed, inserted) in gaps {
+        let mut removed = removed.into_iter().filter(|i| !moved_from[*i]);
+        let mut inserted = inserted.into_iter().filter(|j| !moved_to[*j]);
+        loop {
+            match (removed.next(), inserted.next()) {
+                (Some(i), Some(j)) => {
+                    path.push(PathSegment::Index(j));
+                    diff_into(path, a[i], b[j], changes);
+                    path.pop();
+                }
+                (Some(i), None) => {
+                    path.push(PathSegment::Index(i));
+                    changes.push(Change::Removed {
+                        path: path.clone(),
+                        value: a[i].clone(),
+                    });
+                    path.pop();
+                }
+                (None, Some(j)) => {
+                    path.push(PathSegment::Index(j));
+                    changes.push(Change::Added {
+                        path: path.clone(),
+                        value: b[j].clone(),
+                    });
+                    path.pop();
+                }
+                (None, None) => break,
+            }
+        }
+    }
+}
+
+/// Returns the indices of `a` and `b` that don't take part in the alignment
+/// of the two sequences, grouped by the gap between aligned elements they sit
+/// in.
+pub(crate) fn gaps(a: &[&Value], b: &[&Value]) -> Vec<(Vec<usize>, Vec<usize>)> {
+    let mut gaps = Vec::new();
+    let mut gap: (Vec<usize>, Vec<usize>) = (Vec::new(), Vec::new());
+    for step in align(a, b) {
+        match step {
+            Step::Keep => {
+                if !gap.0.is_empty() || !gap.1.is_empty() {
+                    gaps.push(std::mem::take(&mut gap));
+                }
+            }
+            Step::Remove(i) => gap.0.push(i),
+            Step::Insert(j) => gap.1.push(j),
+        }
+    }
+    if !gap.0.is_empty() || !gap.1.is_empty() {
+        gaps.push(gap);
+    }
+    gaps
+}
+
+enum Step {
+    Keep,
+    Remove(usize),
+    Insert(usize),
+}
+
+/// Aligns two sequences on their longest common subsequence.
+fn align(a: &[&Value], b: &[&Value]) -> Vec<Step> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (ma, mb) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut steps: Vec<Step> = (0..prefix).map(|_| Step::Keep).collect();
+    if ma.len() * mb.len() > MAX_ALIGN_COST {
+        // Too expensive to align, compare position by position.
+        for k in 0..ma.len().max(mb.len()) {
+            if k < ma.len() {
+                steps.push(Step::Remove(prefix + k));
+            }
+            if k < mb.len() {
+                steps.push(Step::Insert(prefix + k));
+            }
+        }
+    } else {
+        // lcs[i][j] is the LCS length of ma[i..] and mb[j..].
+        let w = mb.len() + 1;
+        let mut lcs = vec![0u32; (ma.len() + 1) * w];
+        for i in (0..ma.len()).rev() {
+            for j in (0..mb.len()).rev() {
+                lcs[i * w + j] = if ma[i] == mb[j] {
+                    lcs[(i + 1) * w + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * w + j].max(lcs[i * w + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < ma.len() || j < mb.len() {
+            if i < ma.len() && j < mb.len() && ma[i] == mb[j] {
+                steps.push(Step::Keep);
+                i += 1;
+                j += 1;
+            } else if j < mb.len() && (i == ma.len() || lcs[i * w + j + 1] >= lcs[(i + 1) * w + j])
+            {
+                steps.push(Step::Insert(prefix + j));
+                j += 1;
+            } else {
+                steps.push(Step::Remove(prefix + i));
+                i += 1;
+            }
+        }
+    }
+    steps.extend((0..suffix).map(|_| Step::Keep));
+    steps
+}
+
+struct DisplayPath<'a>(&'a [PathSegment]);
+
+impl<'a> fmt::Display for DisplayPath<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("[")?;
+        for (i, segment) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}", segment)?;
+        }
+        f.write_str("]")
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Added { path, value } => write!(f, "+ {} {}", DisplayPath(path), value),
+            Change::Removed { path, value } => write!(f, "- {} {}", DisplayPath(path), value),
+            Change::Changed { path, old, new } => {
+                write!(f, "~ {} {} -> {}", DisplayPath(path), old, new)
+            }
+            Change::Moved {
+                path,
+                from,
+                to,
+                value,
+            } => write!(f, "> {} {} -> {} {}", DisplayPath(path), from, to, value),
+        }
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_edn;
+
+    fn render(a: &str, b: &str) -> String {
+        diff(&parse_edn(a).unwrap(), &parse_edn(b).unwrap()).to_string()
+    }
+
+    #[test]
+    fn test_diff_maps_and_sets() {
+        assert_eq!(render("{:a 1}", "{:a 1}"), "");
+        assert_eq!(
+            render(
+                "{\"a\" {\"b\" 1 \"c\" 2} \"d\" #{1 2} \"e\" 5}",
+                "{\"a\" {\"b\" 1 \"c\" 3} \"d\" #{2 3} \"f\" 6}"
+            ),
+            "~ [\"a\" \"c\"] 2 -> 3\n- [\"d\"] 1\n+ [\"d\"] 3\n- [\"e\"] 5\n+ [\"f\"] 6\n"
+        );
+        assert_eq!(
+            render("#{1 2 3 :a}", "#{3 4 5 [1]}"),
+            "- [] 1\n- [] 2\n- [] :a\n+ [] 4\n+ [] 5\n+ [] [1]\n"
+        );
+        assert_eq!(render("{:a [1]}", "{:a (1)}"), "~ [:a] [1] -> (1)\n");
+        assert_eq!(render("#foo {:a 1}", "#foo {:a 2}"), "~ [# :a] 1 -> 2\n");
+    }
+
+    #[test]
+    fn test_diff_sequences() {
+        assert_eq!(render("[1 2 3]", "[1 3]"), "- [1] 2\n");
+        assert_eq!(render("[1 3]", "[1 2 3]"), "+ [1] 2\n");
+        assert_eq!(
+            render("[{:id 1 :n 0} 2]", "[{:id 1 :n 1} 2]"),
+            "~ [0 :n] 0 -> 1\n"
+        );
+        assert_eq!(render("[:x :a :b :c]", "[:a :b :c :x]"), "> [] 0 -> 3 :x\n");
+        assert_eq!(
+            render("(1 2 3 4)", "(1 5 3 6 7)"),
+            "~ [1] 2 -> 5\n~ [3] 4 -> 6\n+ [4] 7\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_changes() {
+        let d = diff(
+            &parse_edn("{:v [1 2]}").unwrap(),
+            &parse_edn("{:v [1]}").unwrap(),
+        );
+        assert_eq!(d.len(), 1);
+        assert_eq!(
+            d.into_changes(),
+            vec![Change::Removed {
+                path: vec![PathSegment::Key(":v".into()), PathSegment::Index(1)],
+                value: Value::Integer(2),
+            }]
+        );
+    }
+}
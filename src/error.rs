@@ -9,19 +9,29 @@ pub enum Error {
     Utf8(#[from] std::str::Utf8Error),
     #[error(transparent)]
     Parse(#[from] pest::error::Error<crate::parser::Rule>),
+    #[error("zipper error: {0}")]
+    Zipper(&'static str),
+    #[error("patch error: {0}")]
+    Patch(String),
+    #[error("invalid map key: {0}")]
+    InvalidKey(String),
+    #[error("invalid value: {0}")]
+    InvalidValue(String),
+    #[error("{0}")]
+    Message(String),
 }
 
 /// Alias for a `Result` with the error type `edn::Error`.
 pub type Result<T> = result::Result<T, Error>;
 
 impl serde::ser::Error for Error {
-    fn custom<T: std::fmt::Display>(msg: T) -> Self
-    where
-        T: std::fmt::Display,
-    {
-        Error::Io(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            msg.to_string(),
-        ))
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+
+impl serde::de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
     }
 }
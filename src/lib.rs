@@ -8,19 +8,30 @@
 
 extern crate test;
 
+pub mod diff;
 pub mod error;
 mod macros;
 pub mod map;
 pub mod parser;
+pub mod patch;
 pub mod symbol;
 pub mod value;
+pub mod walk;
+pub mod zip;
 // pub mod ser;
 
 pub use map::Map;
-pub use symbol::Symbol;
-pub use value::Value;
+pub use symbol::{Keyword, Symbol};
+pub use value::{FromEdn, ToEdn, ToEdnKey, Value};
 
-pub use value::to_value;
+pub use diff::diff;
+pub use value::{from_value, to_string_pretty, to_value, to_writer};
+
+#[cfg(feature = "derive")]
+pub use edn_derive::{FromEdn, ToEdn};
+
+#[doc(hidden)]
+pub use value::__private;
 
 #[cfg(test)]
 mod tests {
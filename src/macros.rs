@@ -185,11 +185,11 @@ macro_rules! edn_internal{
     };
 
     (#{}) => {
-        $crate::Value::Set(edn_internal_vec![])
+        $crate::Value::Set(::std::default::Default::default())
     };
 
     ({}) => {
-        $crate::Value::Map(edn_internal_vec![])
+        $crate::Value::Map($crate::Map::new())
     };
 
     ({ $($tt:tt)+ }) => {
@@ -1,18 +1,32 @@
 use crate::value::{Key, Value};
 
+use std::borrow::Borrow;
+#[cfg(not(feature = "persistent-map"))]
+use std::collections::{btree_map, BTreeMap};
 use std::fmt;
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::iter::FusedIterator;
 use std::ops;
-use std::{borrow::Borrow, collections::btree_map, collections::BTreeMap};
 
 /// Represents a EDN key/value type.
+///
+/// By default the entries are kept in a `BTreeMap`. With the
+/// `persistent-map` feature enabled they are kept in a persistent vector
+/// with structural sharing instead, so `clone` and [`assoc`](Map::assoc) are
+/// cheap. Either way the entries iterate in key order.
+///
+/// The feature only covers maps: vectors, lists and sets are the std
+/// collections in [`Value`]'s public variants, and changing their types
+/// under a feature would break code built without it.
 pub struct Map<K, V> {
     map: MapImpl<K, V>,
 }
 
+#[cfg(not(feature = "persistent-map"))]
 type MapImpl<K, V> = BTreeMap<K, V>;
+#[cfg(feature = "persistent-map")]
+type MapImpl<K, V> = persistent::Entries<K, V>;
 
 impl Map<Key, Value> {
     /// Makes a new empty Map.
@@ -116,7 +130,7 @@ impl Map<Key, Value> {
         Key: Borrow<Q>,
         Q: ?Sized + Ord + Eq + Hash,
     {
-        return self.map.remove_entry(key);
+        self.map.remove_entry(key)
     }
 
     /// Moves all elements from other into self, leaving other empty.
@@ -125,12 +139,40 @@ impl Map<Key, Value> {
         self.map.append(&mut other.map);
     }
 
+    /// Returns a new map with the key-value pair inserted, leaving `self`
+    /// untouched.
+    ///
+    /// With the `persistent-map` feature this shares structure with `self` and
+    /// runs in O(log n); otherwise the whole map is cloned first.
+    pub fn assoc(&self, k: Key, v: Value) -> Self {
+        let mut map = self.map.clone();
+        map.insert(k, v);
+        Map { map }
+    }
+
+    /// Returns a new map without the given key, leaving `self` untouched.
+    ///
+    /// With the `persistent-map` feature this shares structure with `self` and
+    /// runs in O(log n); otherwise the whole map is cloned first.
+    pub fn dissoc<Q>(&self, key: &Q) -> Self
+    where
+        Key: Borrow<Q>,
+        Q: ?Sized + Ord + Eq + Hash,
+    {
+        let mut map = self.map.clone();
+        map.remove(key);
+        Map { map }
+    }
+
     /// Gets the given key's corresponding entry in the map for in-place
     /// manipulation.
     pub fn entry<S>(&mut self, key: S) -> Entry
     where
         S: Into<Key>,
     {
+        #[cfg(feature = "persistent-map")]
+        use self::persistent::Entry as EntryImpl;
+        #[cfg(not(feature = "persistent-map"))]
         use std::collections::btree_map::Entry as EntryImpl;
 
         match self.map.entry(key.into()) {
@@ -245,7 +287,7 @@ where
     type Output = Value;
 
     fn index(&self, index: &Q) -> &Value {
-        self.map.index(index)
+        self.map.get(index).expect("no entry found for key")
     }
 }
 
@@ -363,9 +405,15 @@ pub struct OccupiedEntry<'a> {
     occupied: OccupiedEntryImpl<'a>,
 }
 
+#[cfg(not(feature = "persistent-map"))]
 type VacantEntryImpl<'a> = btree_map::VacantEntry<'a, Key, Value>;
+#[cfg(feature = "persistent-map")]
+type VacantEntryImpl<'a> = persistent::VacantEntry<'a, Key, Value>;
 
+#[cfg(not(feature = "persistent-map"))]
 type OccupiedEntryImpl<'a> = btree_map::OccupiedEntry<'a, Key, Value>;
+#[cfg(feature = "persistent-map")]
+type OccupiedEntryImpl<'a> = persistent::OccupiedEntry<'a, Key, Value>;
 
 impl<'a> Entry<'a> {
     /// Returns a reference to this entry's key.
@@ -487,7 +535,10 @@ pub struct Iter<'a> {
     iter: IterImpl<'a>,
 }
 
+#[cfg(not(feature = "persistent-map"))]
 type IterImpl<'a> = btree_map::Iter<'a, Key, Value>;
+#[cfg(feature = "persistent-map")]
+type IterImpl<'a> = persistent::Iter<'a, Key, Value>;
 
 delegate_iterator!((Iter<'a>) => (&'a Key, &'a Value));
 
@@ -509,7 +560,10 @@ pub struct IterMut<'a> {
     iter: IterMutImpl<'a>,
 }
 
+#[cfg(not(feature = "persistent-map"))]
 type IterMutImpl<'a> = btree_map::IterMut<'a, Key, Value>;
+#[cfg(feature = "persistent-map")]
+type IterMutImpl<'a> = persistent::IterMut<'a, Key, Value>;
 
 delegate_iterator!((IterMut<'a>) => (&'a Key, &'a mut Value));
 
@@ -531,7 +585,10 @@ pub struct IntoIter {
     iter: IntoIterImpl,
 }
 
+#[cfg(not(feature = "persistent-map"))]
 type IntoIterImpl = btree_map::IntoIter<Key, Value>;
+#[cfg(feature = "persistent-map")]
+type IntoIterImpl = im::vector::ConsumingIter<(Key, Value)>;
 
 delegate_iterator!((IntoIter) => (Key, Value));
 
@@ -542,7 +599,10 @@ pub struct Keys<'a> {
     iter: KeysImpl<'a>,
 }
 
+#[cfg(not(feature = "persistent-map"))]
 type KeysImpl<'a> = btree_map::Keys<'a, Key, Value>;
+#[cfg(feature = "persistent-map")]
+type KeysImpl<'a> = persistent::Keys<'a, Key, Value>;
 
 delegate_iterator!((Keys<'a>) => &'a Key);
 
@@ -553,7 +613,10 @@ pub struct Values<'a> {
     iter: ValuesImpl<'a>,
 }
 
+#[cfg(not(feature = "persistent-map"))]
 type ValuesImpl<'a> = btree_map::Values<'a, Key, Value>;
+#[cfg(feature = "persistent-map")]
+type ValuesImpl<'a> = persistent::Values<'a, Key, Value>;
 
 delegate_iterator!((Values<'a>) => &'a Value);
 
@@ -564,6 +627,337 @@ pub struct ValuesMut<'a> {
     iter: ValuesMutImpl<'a>,
 }
 
+#[cfg(not(feature = "persistent-map"))]
 type ValuesMutImpl<'a> = btree_map::ValuesMut<'a, Key, Value>;
+#[cfg(feature = "persistent-map")]
+type ValuesMutImpl<'a> = persistent::ValuesMut<'a, Key, Value>;
 
 delegate_iterator!((ValuesMut<'a>) => &'a mut Value);
+
+//////////////////////////////////////////////////////////////////////////////
+
+/// The persistent backend: the entries sorted by key in an RRB vector, which
+/// shares structure between clones and inserts or removes in O(log n).
+///
+/// `im::OrdMap` would do as well, but it can't lend out all of its values
+/// mutably at once, as `iter_mut` and `values_mut` need.
+#[cfg(feature = "persistent-map")]
+mod persistent {
+    use std::borrow::Borrow;
+    use std::fmt;
+
+    use im::vector;
+
+    pub struct Entries<K, V> {
+        entries: im::Vector<(K, V)>,
+    }
+
+    pub type Iter<'a, K, V> =
+        std::iter::Map<vector::Iter<'a, (K, V)>, fn(&'a (K, V)) -> (&'a K, &'a V)>;
+    pub type IterMut<'a, K, V> =
+        std::iter::Map<vector::IterMut<'a, (K, V)>, fn(&'a mut (K, V)) -> (&'a K, &'a mut V)>;
+    pub type Keys<'a, K, V> = std::iter::Map<vector::Iter<'a, (K, V)>, fn(&'a (K, V)) -> &'a K>;
+    pub type Values<'a, K, V> = std::iter::Map<vector::Iter<'a, (K, V)>, fn(&'a (K, V)) -> &'a V>;
+    pub type ValuesMut<'a, K, V> =
+        std::iter::Map<vector::IterMut<'a, (K, V)>, fn(&'a mut (K, V)) -> &'a mut V>;
+
+    impl<K: Ord + Clone, V: Clone> Entries<K, V> {
+        pub fn new() -> Self {
+            Entries {
+                entries: im::Vector::new(),
+            }
+        }
+
+        fn search<Q>(&self, key: &Q) -> Result<usize, usize>
+        where
+            K: Borrow<Q>,
+            Q: ?Sized + Ord,
+        {
+            self.entries.binary_search_by(|(k, _)| k.borrow().cmp(key))
+        }
+
+        pub fn clear(&mut self) {
+            self.entries.clear();
+        }
+
+        pub fn len(&self) -> usize {
+            self.entries.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.entries.is_empty()
+        }
+
+        pub fn get<Q>(&self, key: &Q) -> Option<&V>
+        where
+            K: Borrow<Q>,
+            Q: ?Sized + Ord,
+        {
+            self.get_key_value(key).map(|(_, v)| v)
+        }
+
+        pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+        where
+            K: Borrow<Q>,
+            Q: ?Sized + Ord,
+        {
+            let (k, v) = &self.entries[self.search(key).ok()?];
+            Some((k, v))
+        }
+
+        pub fn contains_key<Q>(&self, key: &Q) -> bool
+        where
+            K: Borrow<Q>,
+            Q: ?Sized + Ord,
+        {
+            self.search(key).is_ok()
+        }
+
+        pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+        where
+            K: Borrow<Q>,
+            Q: ?Sized + Ord,
+        {
+            let index = self.search(key).ok()?;
+            Some(&mut self.entries[index].1)
+        }
+
+        pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+            match self.search(&key) {
+                Ok(index) => Some(std::mem::replace(&mut self.entries[index].1, value)),
+                Err(index) => {
+                    self.entries.insert(index, (key, value));
+                    None
+                }
+            }
+        }
+
+        pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+        where
+            K: Borrow<Q>,
+            Q: ?Sized + Ord,
+        {
+            self.remove_entry(key).map(|(_, v)| v)
+        }
+
+        pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+        where
+            K: Borrow<Q>,
+            Q: ?Sized + Ord,
+        {
+            let index = self.search(key).ok()?;
+            Some(self.entries.remove(index))
+        }
+
+        pub fn append(&mut self, other: &mut Self) {
+            self.extend(std::mem::take(&mut other.entries));
+        }
+
+        pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+            match self.search(&key) {
+                Ok(index) => Entry::Occupied(OccupiedEntry {
+                    entries: &mut self.entries,
+                    index,
+                }),
+                Err(index) => Entry::Vacant(VacantEntry {
+                    entries: &mut self.entries,
+                    index,
+                    key,
+                }),
+            }
+        }
+
+        pub fn retain<F>(&mut self, mut f: F)
+        where
+            F: FnMut(&K, &mut V) -> bool,
+        {
+            self.entries = std::mem::take(&mut self.entries)
+                .into_iter()
+                .filter_map(|(k, mut v)| if f(&k, &mut v) { Some((k, v)) } else { None })
+                .collect();
+        }
+
+        pub fn iter(&self) -> Iter<'_, K, V> {
+            self.entries.iter().map(|(k, v)| (k, v))
+        }
+
+        pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+            self.entries.iter_mut().map(|(k, v)| (&*k, v))
+        }
+
+        pub fn keys(&self) -> Keys<'_, K, V> {
+            self.entries.iter().map(|(k, _)| k)
+        }
+
+        pub fn values(&self) -> Values<'_, K, V> {
+            self.entries.iter().map(|(_, v)| v)
+        }
+
+        pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+            self.entries.iter_mut().map(|(_, v)| v)
+        }
+    }
+
+    impl<K: Clone, V: Clone> Clone for Entries<K, V> {
+        fn clone(&self) -> Self {
+            Entries {
+                entries: self.entries.clone(),
+            }
+        }
+    }
+
+    impl<K: Ord + Clone, V: Clone + PartialEq> PartialEq for Entries<K, V> {
+        fn eq(&self, other: &Self) -> bool {
+            self.entries == other.entries
+        }
+    }
+
+    impl<K: Clone + fmt::Debug, V: Clone + fmt::Debug> fmt::Debug for Entries<K, V> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.debug_map()
+                .entries(self.entries.iter().map(|(k, v)| (k, v)))
+                .finish()
+        }
+    }
+
+    impl<K: Ord + Clone, V: Clone> FromIterator<(K, V)> for Entries<K, V> {
+        fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+            let mut entries = Entries::new();
+            entries.extend(iter);
+            entries
+        }
+    }
+
+    impl<K: Ord + Clone, V: Clone> Extend<(K, V)> for Entries<K, V> {
+        fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+            for (k, v) in iter {
+                self.insert(k, v);
+            }
+        }
+    }
+
+    impl<K: Clone, V: Clone> IntoIterator for Entries<K, V> {
+        type Item = (K, V);
+        type IntoIter = vector::ConsumingIter<(K, V)>;
+
+        fn into_iter(self) -> Self::IntoIter {
+            self.entries.into_iter()
+        }
+    }
+
+    pub enum Entry<'a, K: Clone, V: Clone> {
+        Vacant(VacantEntry<'a, K, V>),
+        Occupied(OccupiedEntry<'a, K, V>),
+    }
+
+    pub struct VacantEntry<'a, K: Clone, V: Clone> {
+        entries: &'a mut im::Vector<(K, V)>,
+        index: usize,
+        key: K,
+    }
+
+    impl<'a, K: Clone, V: Clone> VacantEntry<'a, K, V> {
+        pub fn key(&self) -> &K {
+            &self.key
+        }
+
+        pub fn insert(self, value: V) -> &'a mut V {
+            self.entries.insert(self.index, (self.key, value));
+            &mut self.entries[self.index].1
+        }
+    }
+
+    pub struct OccupiedEntry<'a, K: Clone, V: Clone> {
+        entries: &'a mut im::Vector<(K, V)>,
+        index: usize,
+    }
+
+    impl<'a, K: Clone, V: Clone> OccupiedEntry<'a, K, V> {
+        pub fn key(&self) -> &K {
+            &self.entries[self.index].0
+        }
+
+        pub fn get(&self) -> &V {
+            &self.entries[self.index].1
+        }
+
+        pub fn get_mut(&mut self) -> &mut V {
+            &mut self.entries[self.index].1
+        }
+
+        pub fn into_mut(self) -> &'a mut V {
+            &mut self.entries[self.index].1
+        }
+
+        pub fn insert(&mut self, value: V) -> V {
+            std::mem::replace(self.get_mut(), value)
+        }
+
+        pub fn remove(self) -> V {
+            self.entries.remove(self.index).1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assoc_dissoc() {
+        let mut m1 = Map::new();
+        m1.insert(":a".into(), Value::Integer(1));
+        m1.insert(":b".into(), Value::Integer(2));
+
+        let m2 = m1.assoc(":c".into(), Value::Integer(3));
+        let m3 = m2.dissoc(&Key::from(":a"));
+
+        assert_eq!(m1.len(), 2);
+        assert_eq!(m2.len(), 3);
+        assert_eq!(m3.len(), 2);
+        assert_eq!(m2.get(&Key::from(":c")), Some(&Value::Integer(3)));
+        assert!(!m3.contains_key(&Key::from(":a")));
+        assert!(m1.contains_key(&Key::from(":a")));
+    }
+
+    #[test]
+    fn test_mutation() {
+        let mut m: Map<Key, Value> = (0..10)
+            .map(|i| (Key::Integer(i), Value::Integer(i)))
+            .collect();
+        let snapshot = m.clone();
+
+        for v in m.values_mut() {
+            if let Value::Integer(i) = v {
+                *i *= 10;
+            }
+        }
+        m.retain(|_, v| matches!(v, Value::Integer(i) if *i < 50));
+
+        assert_eq!(m.len(), 5);
+        assert_eq!(m[&Key::Integer(4)], Value::Integer(40));
+        assert_eq!(snapshot.len(), 10);
+        assert_eq!(snapshot[&Key::Integer(4)], Value::Integer(4));
+    }
+
+    #[test]
+    fn test_order() {
+        let mut m = Map::new();
+        for i in [3, 1, 4, 0, 2] {
+            m.entry(Key::Integer(i)).or_insert(Value::Integer(i * 10));
+        }
+        *m.entry(Key::Integer(4)).or_insert(Value::Nil) = Value::Nil;
+        assert_eq!(
+            m.remove_entry(&Key::Integer(0)),
+            Some((Key::Integer(0), Value::Integer(0)))
+        );
+
+        let keys: Vec<_> = m.keys().cloned().collect();
+        assert_eq!(keys, [1, 2, 3, 4].map(Key::Integer));
+        assert_eq!(m.iter().next_back(), Some((&Key::Integer(4), &Value::Nil)));
+        assert_eq!(
+            m.into_iter().rev().map(|(k, _)| k).collect::<Vec<_>>(),
+            [4, 3, 2, 1].map(Key::Integer)
+        );
+    }
+}
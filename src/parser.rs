@@ -1,8 +1,9 @@
+use pest::error::ErrorVariant;
 use pest::iterators::Pair;
 use pest_derive::Parser;
 use uuid::Uuid;
 
-use crate::{error::Result, Map, Value};
+use crate::{error::Result, value::Key, Keyword, Map, Symbol, Value};
 
 #[derive(Parser)]
 #[grammar = "grammars/edn.pest"] // relative to project `src`
@@ -33,6 +34,7 @@ fn unescape_string(s: &str) -> String {
                         Some(b'f') => result.push('\x0C'),
                         Some(b'b') => result.push('\x08'),
                         Some(b'"') => result.push('"'),
+                        Some(b'/') => result.push('/'),
                         Some(b'\\') => result.push('\\'),
                         Some(b'u') => {
                             let code = &s[i + 2..i + 6];
@@ -64,7 +66,9 @@ fn unescape_character(s: &str) -> char {
         "return" => '\r',
         "space" => ' ',
         "tab" => '\t',
-        _ if s.len() == 1 => s.chars().next().unwrap(),
+        "formfeed" => '\x0C',
+        "backspace" => '\x08',
+        _ if s.chars().count() == 1 => s.chars().next().unwrap(),
         _ if s.as_bytes()[0] == b'u' => {
             let code = u32::from_str_radix(&s[1..], 16).unwrap();
             std::char::from_u32(code).unwrap()
@@ -73,42 +77,102 @@ fn unescape_character(s: &str) -> char {
     }
 }
 
-fn parse_value(pair: Pair<Rule>) -> Value {
-    match pair.as_rule() {
+// Applies the namespace of a namespace map to one of its keys
+fn qualify_key(ns: &str, key: Key) -> Key {
+    match key {
+        Key::Keyword(k) => match k.namespace() {
+            Some("_") => Key::Keyword(Keyword::new(None, k.name())),
+            None => Key::Keyword(Keyword::new(Some(ns), k.name())),
+            Some(_) => Key::Keyword(k),
+        },
+        Key::Symbol(s) => match s.namespace() {
+            Some("_") => Key::Symbol(Symbol::new(None, s.name())),
+            None => Key::Symbol(Symbol::new(Some(ns), s.name())),
+            Some(_) => Key::Symbol(s),
+        },
+        key => key,
+    }
+}
+
+fn parse_integer(pair: Pair<Rule>) -> Result<Value> {
+    let s = pair.as_str();
+    let value = match s.strip_suffix('N') {
+        Some(digits) => digits.parse().map(Value::BigInt),
+        None => s
+            .parse()
+            .map(Value::Integer)
+            .or_else(|_| s.parse().map(Value::BigInt)),
+    };
+    value.map_err(|_| {
+        pest::error::Error::new_from_span(
+            ErrorVariant::CustomError {
+                message: format!("integer {} is outside the range of 128-bit big integers", s),
+            },
+            pair.as_span(),
+        )
+        .into()
+    })
+}
+
+// Parses the children of a collection, skipping discarded ones
+fn parse_all<'a>(
+    pair: Pair<'a, Rule>,
+    options: &'a ParserOptions,
+) -> impl Iterator<Item = Result<Value>> + 'a {
+    pair.into_inner()
+        .filter(|p| p.as_rule() != Rule::discard)
+        .map(|p| parse_value(p, options))
+}
+
+fn parse_value(pair: Pair<Rule>, options: &ParserOptions) -> Result<Value> {
+    let value = match pair.as_rule() {
         Rule::nil => Value::Nil,
         Rule::boolean => Value::Boolean(pair.as_str() == "true"),
-        Rule::integer => Value::Integer(pair.as_str().parse().unwrap()),
+        Rule::integer => parse_integer(pair)?,
         Rule::float => Value::Float(pair.as_str().parse().unwrap()),
+        Rule::symbolic => Value::Float(
+            match pair.as_str() {
+                "##NaN" => f64::NAN,
+                "##Inf" => f64::INFINITY,
+                _ => f64::NEG_INFINITY,
+            }
+            .into(),
+        ),
         Rule::string => Value::String(unescape_string(pair.as_str())),
         Rule::symbol => Value::Symbol(pair.as_str().into()),
         Rule::keyword => Value::Keyword(pair.as_str().into()),
-        Rule::vector => Value::Vector(
-            pair.into_inner()
-                .filter(|p| p.as_rule() != Rule::discard)
-                .map(parse_value)
-                .collect(),
-        ),
-        Rule::list => Value::List(
-            pair.into_inner()
-                .filter(|p| p.as_rule() != Rule::discard)
-                .map(parse_value)
-                .collect(),
-        ),
-        Rule::set => Value::Set(
-            pair.into_inner()
-                .filter(|p| p.as_rule() != Rule::discard)
-                .map(parse_value)
-                .collect(),
-        ),
+        Rule::auto_keyword => {
+            let name = &pair.as_str()[2..];
+            match &options.namespace {
+                Some(ns) if !name.contains('/') => Value::Keyword(Keyword::new(Some(ns), name)),
+                _ => {
+                    return Err(pest::error::Error::new_from_span(
+                        ErrorVariant::CustomError {
+                            message: format!("can't resolve keyword {}", pair.as_str()),
+                        },
+                        pair.as_span(),
+                    )
+                    .into())
+                }
+            }
+        }
+        Rule::vector => Value::Vector(parse_all(pair, options).collect::<Result<_>>()?),
+        Rule::list => Value::List(parse_all(pair, options).collect::<Result<_>>()?),
+        Rule::set => Value::Set(parse_all(pair, options).collect::<Result<_>>()?),
         Rule::map => {
             let mut map = Map::new();
-            let mut pairs = pair.into_inner();
+            let mut pairs = pair.into_inner().filter(|p| p.as_rule() != Rule::discard);
             loop {
                 match pairs.next_chunk() {
                     Ok([key, value]) => {
-                        let key = parse_value(key);
-                        let value = parse_value(value);
-                        map.insert(key.try_into().unwrap(), value);
+                        let span = key.as_span();
+                        let key = Key::try_from(parse_value(key, options)?).map_err(|message| {
+                            pest::error::Error::new_from_span(
+                                ErrorVariant::CustomError { message },
+                                span,
+                            )
+                        })?;
+                        map.insert(key, parse_value(value, options)?);
                     }
                     Err(it) => {
                         if it.count() != 0 {
@@ -120,6 +184,18 @@ fn parse_value(pair: Pair<Rule>) -> Value {
             }
             Value::Map(map)
         }
+        Rule::namespaced_map => {
+            let mut inner = pair.into_inner();
+            let ns = inner.next().unwrap().as_str();
+            let Value::Map(map) = parse_value(inner.next().unwrap(), options)? else {
+                unreachable!()
+            };
+            Value::Map(
+                map.into_iter()
+                    .map(|(k, v)| (qualify_key(ns, k), v))
+                    .collect(),
+            )
+        }
         Rule::character => Value::Character(unescape_character(&pair.as_str()[1..])),
         Rule::tagged => {
             let mut tagged = pair.into_inner();
@@ -136,20 +212,154 @@ fn parse_value(pair: Pair<Rule>) -> Value {
                 let inst = chrono::DateTime::parse_from_rfc3339(val).unwrap();
                 Value::Instant(inst)
             } else {
-                Value::Tagged(tag.into(), Box::new(parse_value(tagged.next().unwrap())))
+                Value::Tagged(
+                    tag.into(),
+                    Box::new(parse_value(tagged.next().unwrap(), options)?),
+                )
             }
         }
         _ => {
             unreachable!()
         }
+    };
+    Ok(value)
+}
+
+/// Reader settings.
+///
+/// ```
+/// use edn::{parser::ParserOptions, Keyword, Value};
+///
+/// let options = ParserOptions::new().namespace("user");
+/// assert_eq!(
+///     options.parse("::id").unwrap(),
+///     Value::Keyword(Keyword::new(Some("user"), "id"))
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ParserOptions {
+    namespace: Option<String>,
+}
+
+impl ParserOptions {
+    /// Creates the default settings, with no current namespace.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the current namespace, which auto-resolved keywords such as
+    /// `::id` are read in. Without one they are an error.
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Parses a single EDN value.
+    pub fn parse(&self, input: &str) -> Result<Value> {
+        use pest::Parser;
+
+        let edn = EDNParser::parse(Rule::edn, input)?.next().unwrap();
+        parse_value(edn, self)
     }
 }
 
-pub fn parse_edn(input: &str) -> Result<Value> {
+/// Checks that the whole of `input` matches `rule`.
+pub(crate) fn check(rule: Rule, input: &str) -> Result<()> {
     use pest::Parser;
 
-    let edn = EDNParser::parse(Rule::edn, input)?.next().unwrap();
-    let val = parse_value(edn);
+    EDNParser::parse(rule, input)?;
+    Ok(())
+}
+
+pub fn parse_edn(input: &str) -> Result<Value> {
+    ParserOptions::new().parse(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
 
-    Ok(val)
+    #[test]
+    fn test_auto_keyword() {
+        let options = ParserOptions::new().namespace("app.core");
+        assert_eq!(
+            options.parse("{::id 1 :id 2}").unwrap(),
+            parse_edn("{:app.core/id 1 :id 2}").unwrap()
+        );
+        assert!(parse_edn("::id").is_err());
+        assert!(options.parse("::alias/id").is_err());
+    }
+
+    #[test]
+    fn test_literal_prefixes() {
+        for s in ["nilly", "trueish", "falsey", "nil/x", "true.core/x", "nil?"] {
+            assert_eq!(parse_edn(s).unwrap(), Value::Symbol(s.into()), "{}", s);
+        }
+        assert_eq!(
+            parse_edn("[nilly nil true false?]").unwrap(),
+            Value::Vector(vec![
+                Value::Symbol("nilly".into()),
+                Value::Nil,
+                Value::Boolean(true),
+                Value::Symbol("false?".into()),
+            ])
+        );
+        assert_eq!(parse_edn("[nil]").unwrap(), Value::Vector(vec![Value::Nil]));
+        assert_eq!(
+            parse_edn("(true)").unwrap(),
+            Value::List(vec![Value::Boolean(true)])
+        );
+    }
+
+    #[test]
+    fn test_big_integers() {
+        assert_eq!(parse_edn("1N").unwrap(), Value::BigInt(1));
+        assert_eq!(
+            parse_edn("9223372036854775807").unwrap(),
+            Value::Integer(i64::MAX)
+        );
+        assert_eq!(
+            parse_edn("-9223372036854775809").unwrap(),
+            Value::BigInt(i64::MIN as i128 - 1)
+        );
+        assert_eq!(
+            parse_edn("9223372036854775808").unwrap().to_string(),
+            "9223372036854775808N"
+        );
+        for s in [
+            "170141183460469231731687303715884105728",
+            "123456789012345678901234567890123456789012N",
+        ] {
+            let e = parse_edn(s).unwrap_err().to_string();
+            assert!(
+                e.contains("outside the range of 128-bit big integers"),
+                "{}",
+                e
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_map_keys() {
+        for (s, key) in [
+            ("{1N 2}", "1N"),
+            ("{9223372036854775808 1}", "9223372036854775808"),
+            ("{[1] 2}", "[1]"),
+            ("{nil 1}", "nil"),
+            ("{:a 1 2.5 3}", "2.5"),
+        ] {
+            let Err(Error::Parse(e)) = parse_edn(s) else {
+                panic!("{} should be an error", s)
+            };
+            let start = s.find(key).unwrap();
+            assert_eq!(
+                e.location,
+                pest::error::InputLocation::Span((start, start + key.len())),
+                "{}",
+                s
+            );
+        }
+        assert!(parse_edn("{:a {nil 1}}").is_err());
+    }
 }
@@ -0,0 +1,419 @@
+//! Patches: edits to a `Value`, written as EDN.
+//!
+//! A patch is a vector of operations, each a vector starting with a keyword
+//! followed by a path and, for most operations, a value:
+//!
+//! ```edn
+//! [[:assoc [:a :b] 1]     ; set a map entry, or replace a sequence element
+//!  [:dissoc [:c]]         ; remove a map entry or a sequence element
+//!  [:insert [:v 0] 2]     ; insert a sequence element, shifting later ones
+//!  [:conj [:v] 3]         ; append to a vector, prepend to a list, add to a set
+//!  [:disj [:tags] :old]]  ; remove a set member
+//! ```
+//!
+//! Paths are vectors of map keys and sequence indices. The empty path is the
+//! value itself, so `[:assoc [] x]` replaces the whole value.
+//!
+//! ```
+//! use edn::{parser::parse_edn, patch::{self, Patch}};
+//!
+//! let mut doc = parse_edn("{:a {:b 0} :c 1 :v [1 2]}").unwrap();
+//! let p: Patch = "[[:assoc [:a :b] 1] [:dissoc [:c]] [:conj [:v] 3]]".parse().unwrap();
+//! patch::apply(&mut doc, &p).unwrap();
+//! assert_eq!(doc, parse_edn("{:a {:b 1} :v [1 2 3]}").unwrap());
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::diff;
+use crate::error::{Error, Result};
+use crate::parser::parse_edn;
+use crate::value::Key;
+use crate::Value;
+
+/// A single patch operation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Op {
+    /// Sets the map entry at the path, or replaces the sequence element at
+    /// the path. An index one past the end appends.
+    Assoc(Vec<Key>, Value),
+    /// Removes the map entry or the sequence element at the path.
+    Dissoc(Vec<Key>),
+    /// Inserts a sequence element at the path.
+    Insert(Vec<Key>, Value),
+    /// Adds a value to the vector, list or set at the path.
+    Conj(Vec<Key>, Value),
+    /// Removes a member from the set at the path.
+    Disj(Vec<Key>, Value),
+}
+
+/// A list of operations, applied in order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Patch {
+    ops: Vec<Op>,
+}
+
+impl Patch {
+    /// Creates an empty patch.
+    pub fn new() -> Self {
+        Patch { ops: Vec::new() }
+    }
+
+    /// Appends an operation.
+    pub fn push(&mut self, op: Op) {
+        self.ops.push(op)
+    }
+
+    /// Returns the operations.
+    pub fn ops(&self) -> &[Op] {
+        &self.ops
+    }
+
+    /// Returns true if the patch has no operations.
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+impl FromIterator<Op> for Patch {
+    fn from_iter<I: IntoIterator<Item = Op>>(iter: I) -> Self {
+        Patch {
+            ops: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Applies `patch` to `value`.
+///
+/// Either every operation succeeds, or `value` is left untouched and the
+/// first failing operation is reported.
+pub fn apply(value: &mut Value, patch: &Patch) -> Result<()> {
+    let mut result = value.clone();
+    for (n, op) in patch.ops.iter().enumerate() {
+        apply_op(&mut result, op).map_err(|e| Error::Patch(format!("op {} {}: {}", n, op, e)))?;
+    }
+    *value = result;
+    Ok(())
+}
+
+/// Generates a patch that turns `a` into `b`.
+pub fn generate(a: &Value, b: &Value) -> Patch {
+    let mut patch = Patch::new();
+    generate_into(&mut Vec::new(), a, b, &mut patch.ops);
+    patch
+}
+
+fn generate_into(path: &mut Vec<Key>, a: &Value, b: &Value, ops: &mut Vec<Op>) {
+    if a == b {
+        return;
+    }
+    match (a, b) {
+        (Value::Map(ma), Value::Map(mb)) => {
+            for (k, va) in ma {
+                path.push(k.clone());
+                match mb.get(k) {
+                    Some(vb) => generate_into(path, va, vb, ops),
+                    None => ops.push(Op::Dissoc(path.clone())),
+                }
+                path.pop();
+            }
+            for (k, vb) in mb.iter().filter(|(k, _)| !ma.contains_key(k)) {
+                path.push(k.clone());
+                ops.push(Op::Assoc(path.clone(), vb.clone()));
+                path.pop();
+            }
+        }
+        (Value::Set(sa), Value::Set(sb)) => {
+            for v in diff::set_difference(sa, sb) {
+                ops.push(Op::Disj(path.clone(), v.clone()));
+            }
+            for v in diff::set_difference(sb, sa) {
+                ops.push(Op::Conj(path.clone(), v.clone()));
+            }
+        }
+        (Value::Vector(va), Value::Vector(vb)) | (Value::List(va), Value::List(vb)) => {
+            let va: Vec<&Value> = va.iter().collect();
+            let vb: Vec<&Value> = vb.iter().collect();
+            // Elements left over on one side of a gap are removed from the
+            // back, so earlier indices stay valid, then inserted from the
+            // front. What remains lines up with the new sequence.
+            let mut removed = Vec::new();
+            let mut inserted = Vec::new();
+            let mut paired = Vec::new();
+            for (rem, ins) in diff::gaps(&va, &vb) {
+                let n = rem.len().min(ins.len());
+                paired.extend(rem[..n].iter().copied().zip(ins[..n].iter().copied()));
+                removed.extend_from_slice(&rem[n..]);
+                inserted.extend_from_slice(&ins[n..]);
+            }
+            for i in removed.into_iter().rev() {
+                path.push(Key::Integer(i as i64));
+                ops.push(Op::Dissoc(path.clone()));
+                path.pop();
+            }
+            for j in inserted {
+                path.push(Key::Integer(j as i64));
+                ops.push(Op::Insert(path.clone(), vb[j].clone()));
+                path.pop();
+            }
+            for (i, j) in paired {
+                path.push(Key::Integer(j as i64));
+                generate_into(path, va[i], vb[j], ops);
+                path.pop();
+            }
+        }
+        _ => ops.push(Op::Assoc(path.clone(), b.clone())),
+    }
+}
+
+fn apply_op(value: &mut Value, op: &Op) -> std::result::Result<(), String> {
+    match op {
+        Op::Assoc(path, v) => {
+            let Some((last, parent)) = path.split_last() else {
+                *value = v.clone();
+                return Ok(());
+            };
+            match get_in_mut(value, parent)? {
+                Value::Map(m) => {
+                    m.insert(last.clone(), v.clone());
+                }
+                Value::Vector(seq) | Value::List(seq) => {
+                    let i = index(last, seq.len() + 1)?;
+                    if i == seq.len() {
+                        seq.insert(i, v.clone());
+                    } else {
+                        seq[i] = v.clone();
+                    }
+                }
+                other => return Err(format!("can't assoc into {}", other)),
+            }
+        }
+        Op::Dissoc(path) => {
+            let (last, parent) = path.split_last().ok_or("can't dissoc the root")?;
+            match get_in_mut(value, parent)? {
+                Value::Map(m) => {
+                    m.remove(last).ok_or_else(|| format!("no entry {}", last))?;
+                }
+                Value::Vector(seq) | Value::List(seq) => {
+                    let i = index(last, seq.len())?;
+                    seq.remove(i);
+                }
+                other => return Err(format!("can't dissoc from {}", other)),
+            }
+        }
+        Op::Insert(path, v) => {
+            let (last, parent) = path.split_last().ok_or("can't insert at the root")?;
+            match get_in_mut(value, parent)? {
+                Value::Vector(seq) | Value::List(seq) => {
+                    let i = index(last, seq.len() + 1)?;
+                    seq.insert(i, v.clone());
+                }
+                other => return Err(format!("can't insert into {}", other)),
+            }
+        }
+        Op::Conj(path, v) => match get_in_mut(value, path)? {
+            Value::Vector(seq) => seq.insert(seq.len(), v.clone()),
+            Value::List(seq) => seq.insert(0, v.clone()),
+            Value::Set(s) => {
+                s.insert(v.clone());
+            }
+            other => return Err(format!("can't conj onto {}", other)),
+        },
+        Op::Disj(path, v) => match get_in_mut(value, path)? {
+            Value::Set(s) => {
+                if !s.contains(v) {
+                    return Err(format!("no member {}", v));
+                }
+                s.remove(v);
+            }
+            other => return Err(format!("can't disj from {}", other)),
+        },
+    }
+    Ok(())
+}
+
+fn get_in_mut<'a>(
+    mut value: &'a mut Value,
+    path: &[Key],
+) -> std::result::Result<&'a mut Value, String> {
+    for key in path {
+        value = match value {
+            Value::Map(m) => m.get_mut(key).ok_or_else(|| format!("no entry {}", key))?,
+            Value::Vector(seq) | Value::List(seq) => {
+                let i = index(key, seq.len())?;
+                &mut seq[i]
+            }
+            other => return Err(format!("can't look up {} in {}", key, other)),
+        };
+    }
+    Ok(value)
+}
+
+/// Checks that `key` is an index below `len`.
+fn index(key: &Key, len: usize) -> std::result::Result<usize, String> {
+    match key {
+        Key::Integer(i) if (0..len as i64).contains(i) => Ok(*i as usize),
+        Key::Integer(i) => Err(format!("index {} out of bounds", i)),
+        _ => Err(format!("{} is not an index", key)),
+    }
+}
+
+impl Op {
+    fn name(&self) -> &'static str {
+        match self {
+            Op::Assoc(..) => "assoc",
+            Op::Dissoc(..) => "dissoc",
+            Op::Insert(..) => "insert",
+            Op::Conj(..) => "conj",
+            Op::Disj(..) => "disj",
+        }
+    }
+}
+
+impl From<Op> for Value {
+    fn from(op: Op) -> Self {
+        let name = Value::Keyword(op.name().into());
+        let (path, value) = match op {
+            Op::Dissoc(path) => (path, None),
+            Op::Assoc(path, v) | Op::Insert(path, v) | Op::Conj(path, v) | Op::Disj(path, v) => {
+                (path, Some(v))
+            }
+        };
+        let path = Value::Vector(path.into_iter().map(Value::from).collect());
+        Value::Vector([name, path].into_iter().chain(value).collect())
+    }
+}
+
+impl From<Patch> for Value {
+    fn from(patch: Patch) -> Self {
+        Value::Vector(patch.ops.into_iter().map(Value::from).collect())
+    }
+}
+
+impl TryFrom<Value> for Op {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self> {
+        let invalid = || Error::Patch(format!("invalid operation {}", value));
+        let Value::Vector(parts) = &value else {
+            return Err(invalid());
+        };
+        let mut parts = parts.iter().cloned();
+        let (Some(Value::Keyword(name)), Some(Value::Vector(path))) = (parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        let path = path
+            .into_iter()
+            .map(Key::try_from)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(Error::Patch)?;
+        let op = match (name.as_str(), parts.next(), parts.next()) {
+            ("dissoc", None, _) => Op::Dissoc(path),
+            ("assoc", Some(v), None) => Op::Assoc(path, v),
+            ("insert", Some(v), None) => Op::Insert(path, v),
+            ("conj", Some(v), None) => Op::Conj(path, v),
+            ("disj", Some(v), None) => Op::Disj(path, v),
+            _ => return Err(invalid()),
+        };
+        Ok(op)
+    }
+}
+
+impl TryFrom<Value> for Patch {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self> {
+        match value {
+            Value::Vector(ops) => ops.into_iter().map(Op::try_from).collect(),
+            _ => Err(Error::Patch(format!("a patch must be a vector: {}", value))),
+        }
+    }
+}
+
+impl FromStr for Patch {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        parse_edn(s)?.try_into()
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Value::from(self.clone()))
+    }
+}
+
+impl fmt::Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Value::from(self.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edn(s: &str) -> Value {
+        parse_edn(s).unwrap()
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut doc = edn("{:a [1 2 3] :s #{1} :l (2)}");
+        let p: Patch = "[[:assoc [:a 3] 4] [:dissoc [:a 0]] [:insert [:a 0] 0] \
+                        [:conj [:s] 2] [:disj [:s] 1] [:conj [:l] 1] [:assoc [:b] {}] \
+                        [:assoc [:b :c] nil]]"
+            .parse()
+            .unwrap();
+        apply(&mut doc, &p).unwrap();
+        assert_eq!(doc, edn("{:a [0 2 3 4] :s #{2} :l (1 2) :b {:c nil}}"));
+
+        // A failing operation leaves the value untouched.
+        let p: Patch = "[[:assoc [:x] 1] [:dissoc [:a 10]]]".parse().unwrap();
+        let err = apply(&mut doc, &p).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "patch error: op 1 [:dissoc [:a 10]]: index 10 out of bounds"
+        );
+        assert_eq!(doc, edn("{:a [0 2 3 4] :s #{2} :l (1 2) :b {:c nil}}"));
+
+        assert!("[[:assoc [:a]]]".parse::<Patch>().is_err());
+        assert!("[[:move [:a] [:b]]]".parse::<Patch>().is_err());
+    }
+
+    #[test]
+    fn test_generate() {
+        let cases = [
+            ("{:a 1 :b {:c [1 2 3]}}", "{:a 2 :b {:c [1 3 4]} :d #{1}}"),
+            ("[:x 1 2 3 {:a 1}]", "[1 2 {:a 2} 3 :x]"),
+            ("(1 2 3)", "(0 1 2)"),
+            ("#{1 2}", "#{2 3}"),
+            ("[[1 2] [3 4]]", "[[2] [3 4 5] [6]]"),
+            ("{:a [1]}", "{:a (1)}"),
+            ("1", "{:a 1}"),
+        ];
+        for (a, b) in cases {
+            let (mut a, b) = (edn(a), edn(b));
+            let p = generate(&a, &b);
+            // Patches survive a round trip through their EDN form.
+            let p: Patch = p.to_string().parse().unwrap();
+            apply(&mut a, &p).unwrap();
+            assert_eq!(a, b, "{}", p);
+        }
+        assert_eq!(
+            generate(
+                &edn("{\"a\" {\"b\" 1} \"c\" 2 \"d\" 3}"),
+                &edn("{\"a\" {\"b\" 2} \"e\" 4}")
+            )
+            .to_string(),
+            "[[:assoc [\"a\" \"b\"] 2] [:dissoc [\"c\"]] [:dissoc [\"d\"]] [:assoc [\"e\"] 4]]"
+        );
+        assert_eq!(
+            generate(&edn("#{1 2 :a}"), &edn("#{3 [1] \"s\" :a}")).to_string(),
+            "[[:disj [] 1] [:disj [] 2] [:conj [] \"s\"] [:conj [] 3] [:conj [] [1]]]"
+        );
+    }
+}
@@ -4,47 +4,183 @@ use std::cmp::Ordering;
 use std::collections::BTreeSet;
 use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
 use std::hash::Hash;
-use std::mem::{forget, transmute};
 use std::ops::Deref;
 use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
-use std::sync::Mutex;
+use std::sync::{Arc, RwLock, RwLockWriteGuard};
 
-static mut SYMBOL_HEAP: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+use crate::error::Result;
+use crate::parser::{self, Rule};
+
+/// Number of independently locked parts of the symbol table. A power of two.
+const SHARDS: usize = 64;
+
+/// The interned strings, split by hash so that threads interning different
+/// strings rarely wait for each other. Looking up a string that is already
+/// interned only takes a read lock.
+///
+/// The table holds one reference to each string and every [`Symbol`]
+/// another, so a string is removed with the last symbol naming it.
+static SYMBOL_HEAP: [Shard; SHARDS] = [const {
+    Shard(RwLock::new(Heap {
+        names: BTreeSet::new(),
+        sweep_at: MIN_SWEEP,
+    }))
+}; SHARDS];
+
+/// Shard size below which unused names aren't swept.
+const MIN_SWEEP: usize = 64;
+
+/// Aligned to a cache line so that threads using different shards don't
+/// contend on the same line.
+#[repr(align(128))]
+struct Shard(RwLock<Heap>);
+
+struct Heap {
+    names: BTreeSet<Arc<str>>,
+    /// Size at which to look for names no symbol uses any more. A symbol
+    /// normally removes its name when dropped, but two copies dropped at
+    /// the same time can both see the other and leave it behind.
+    sweep_at: usize,
+}
+
+impl Heap {
+    fn insert(&mut self, s: Arc<str>) {
+        if self.names.len() >= self.sweep_at {
+            self.names.retain(|s| Arc::strong_count(s) > 1);
+            self.sweep_at = (self.names.len() * 2).max(MIN_SWEEP);
+        }
+        self.names.insert(s);
+    }
+}
+
+fn shard(s: &str) -> &'static Shard {
+    // FNV-1a; cheaper than SipHash for short names, and good enough to
+    // spread them over the shards.
+    let hash = s.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x100000001b3)
+    });
+    &SYMBOL_HEAP[hash as usize & (SHARDS - 1)]
+}
+
+impl Shard {
+    fn write(&self) -> RwLockWriteGuard<'_, Heap> {
+        // A poisoned lock can't hold a half-inserted string, so keep using it.
+        self.0.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Returns the interned copy of `s`, interning it if needed. The second
+/// value is true if `s` was not interned before.
+fn intern(s: &str) -> (Arc<str>, bool) {
+    let shard = shard(s);
+    if let Some(s) = shard
+        .0
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .names
+        .get(s)
+    {
+        return (s.clone(), false);
+    }
+    let mut heap = shard.write();
+    // Another thread may have interned it since the read lock was released.
+    match heap.names.get(s) {
+        Some(s) => (s.clone(), false),
+        None => {
+            let s: Arc<str> = s.into();
+            heap.insert(s.clone());
+            (s, true)
+        }
+    }
+}
 
 /// An interned string with O(1) equality.
-#[derive(Clone, Copy, Eq)]
+///
+/// Symbols are reference counted: the string is freed when the last symbol
+/// naming it is dropped.
+#[derive(Clone, Eq)]
 pub struct Symbol {
-    s: &'static str,
+    s: Arc<str>,
 }
 
 impl Symbol {
     /// Retrieves the address of the backing string.
     #[inline(always)]
-    fn addr(self) -> usize {
+    fn addr(&self) -> usize {
         self.s.as_ptr() as usize
     }
 
+    /// Creates the symbol `namespace/name`, or `name` without a namespace.
+    ///
+    /// ```
+    /// use edn::Symbol;
+    ///
+    /// assert_eq!(Symbol::new(Some("clojure.core"), "map"), "clojure.core/map");
+    /// assert_eq!(Symbol::new(None, "map"), "map");
+    /// ```
+    pub fn new(namespace: Option<&str>, name: &str) -> Symbol {
+        match namespace {
+            Some(ns) => Symbol::from(format!("{}/{}", ns, name)),
+            None => Symbol::from(name),
+        }
+    }
+
+    /// Creates a symbol, checking that `s` is a valid EDN symbol such as
+    /// `foo` or `clojure.core/map`.
+    ///
+    /// [`Symbol::from`] accepts any string, even ones that won't read back.
+    ///
+    /// ```
+    /// use edn::Symbol;
+    ///
+    /// assert!(Symbol::parse("clojure.core/map").is_ok());
+    /// assert!(Symbol::parse("hello world").is_err());
+    /// assert!(Symbol::parse(" map").is_err());
+    /// assert!(Symbol::parse("nil").is_err());
+    /// ```
+    pub fn parse(s: &str) -> Result<Symbol> {
+        parser::check(Rule::symbol_name, s)?;
+        Ok(Symbol::from(s))
+    }
+
     /// Retrieves the string from the Symbol.
     #[inline(always)]
-    pub fn as_str(self) -> &'static str {
-        self.s
+    pub fn as_str(&self) -> &str {
+        &self.s
+    }
+
+    /// The namespace of the symbol: `clojure.core` for `clojure.core/map`.
+    pub fn namespace(&self) -> Option<&str> {
+        split_namespace(&self.s).0
+    }
+
+    /// The name of the symbol, without its namespace: `map` for
+    /// `clojure.core/map`.
+    pub fn name(&self) -> &str {
+        split_namespace(&self.s).1
     }
 
     /// Generates a new symbol with a name of the form `G#n`, where `n` is some positive integer.
     pub fn gensym() -> Symbol {
-        static mut N: AtomicUsize = AtomicUsize::new(0);
-
-        unsafe {
-            if let Ok(mut heap) = SYMBOL_HEAP.lock() {
-                let n = loop {
-                    let n = leak_string(format!("G#{}", N.fetch_add(1, AtomicOrdering::SeqCst)));
-                    if heap.insert(n) {
-                        break n;
-                    }
-                };
-                Symbol::from(n)
-            } else {
-                unreachable!("failed to lock symbol heap")
+        static N: AtomicUsize = AtomicUsize::new(0);
+
+        loop {
+            let n = format!("G#{}", N.fetch_add(1, AtomicOrdering::Relaxed));
+            if let (s, true) = intern(&n) {
+                break Symbol { s };
+            }
+        }
+    }
+}
+
+impl Drop for Symbol {
+    fn drop(&mut self) {
+        // Only the table and this symbol hold the string. New symbols are
+        // only made under the shard lock, so check again once it's held.
+        if Arc::strong_count(&self.s) == 2 {
+            let mut heap = shard(&self.s).write();
+            if Arc::strong_count(&self.s) == 2 {
+                heap.names.remove(&*self.s);
             }
         }
     }
@@ -52,20 +188,20 @@ impl Symbol {
 
 impl Debug for Symbol {
     fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
-        Debug::fmt(self.s, fmt)
+        Debug::fmt(&*self.s, fmt)
     }
 }
 
 impl Deref for Symbol {
     type Target = str;
     fn deref(&self) -> &str {
-        self.s
+        &self.s
     }
 }
 
 impl Display for Symbol {
     fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
-        fmt.write_str(self.s)
+        fmt.write_str(&self.s)
     }
 }
 
@@ -77,33 +213,26 @@ impl Hash for Symbol {
 
 impl<S: AsRef<str>> From<S> for Symbol {
     fn from(s: S) -> Symbol {
-        let s = s.as_ref();
-        if let Ok(mut heap) = unsafe { SYMBOL_HEAP.lock() } {
-            match heap.get(s) {
-                Some(s) => Symbol { s },
-                None => {
-                    let s = leak_string(s.to_owned());
-                    heap.insert(s);
-                    return Symbol { s };
-                }
-            }
-        } else {
-            unreachable!("failed to lock symbol heap")
+        Symbol {
+            s: intern(s.as_ref()).0,
         }
     }
 }
 
+/// Orders symbols by namespace, with symbols without one first, then by
+/// name.
 impl Ord for Symbol {
     fn cmp(&self, other: &Self) -> Ordering {
-        let l = self.addr();
-        let r = other.addr();
-        l.cmp(&r)
+        if self == other {
+            return Ordering::Equal;
+        }
+        split_namespace(self).cmp(&split_namespace(other))
     }
 }
 
 impl PartialEq for Symbol {
     fn eq(&self, other: &Self) -> bool {
-        self.cmp(other) == Ordering::Equal
+        self.addr() == other.addr()
     }
 }
 
@@ -121,14 +250,105 @@ impl<S: AsRef<str>> PartialEq<S> for Symbol {
 
 impl<S: AsRef<str>> PartialOrd<S> for Symbol {
     fn partial_cmp(&self, other: &S) -> Option<Ordering> {
-        self.s.partial_cmp(other.as_ref())
+        (*self.s).partial_cmp(other.as_ref())
+    }
+}
+
+/// An interned keyword, such as `:block/uuid`, with O(1) equality.
+///
+/// The keyword's text, as returned by [`Keyword::as_str`], does not include
+/// the leading `:`; [`Display`] adds it back.
+///
+/// ```
+/// use edn::Keyword;
+///
+/// let k = Keyword::new(Some("block"), "uuid");
+/// assert_eq!(k.namespace(), Some("block"));
+/// assert_eq!(k.name(), "uuid");
+/// assert_eq!(k.to_string(), ":block/uuid");
+/// assert_eq!(k, Keyword::from(":block/uuid"));
+/// ```
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Keyword(Symbol);
+
+impl Keyword {
+    /// Creates the keyword `:namespace/name`, or `:name` without a namespace.
+    pub fn new(namespace: Option<&str>, name: &str) -> Keyword {
+        Keyword(Symbol::new(namespace, name))
+    }
+
+    /// Creates a keyword, checking that `s` is a valid EDN keyword, with or
+    /// without the leading `:`.
+    ///
+    /// [`Keyword::from`] accepts any string, even ones that won't read back.
+    ///
+    /// ```
+    /// use edn::Keyword;
+    ///
+    /// assert_eq!(Keyword::parse(":block/uuid").unwrap(), Keyword::new(Some("block"), "uuid"));
+    /// assert!(Keyword::parse(":").is_err());
+    /// assert!(Keyword::parse("::id").is_err());
+    /// assert!(Keyword::parse(":hello world").is_err());
+    /// ```
+    pub fn parse(s: &str) -> Result<Keyword> {
+        parser::check(Rule::keyword_name, s)?;
+        Ok(Keyword::from(s))
+    }
+
+    /// Retrieves the text of the keyword, without the leading `:`.
+    #[inline(always)]
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// The namespace of the keyword: `block` for `:block/uuid`.
+    pub fn namespace(&self) -> Option<&str> {
+        self.0.namespace()
+    }
+
+    /// The name of the keyword, without its namespace: `uuid` for
+    /// `:block/uuid`.
+    pub fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    /// The symbol with the same namespace and name.
+    pub fn to_symbol(&self) -> Symbol {
+        self.0.clone()
+    }
+}
+
+impl Debug for Keyword {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        Debug::fmt(&self.to_string(), fmt)
     }
 }
 
-fn leak_string(s: String) -> &'static str {
-    let out = unsafe { transmute(&s as &str) };
-    forget(s);
-    out
+impl Display for Keyword {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        write!(fmt, ":{}", self.0)
+    }
+}
+
+/// Creates a keyword from its text, with or without the leading `:`.
+impl<S: AsRef<str>> From<S> for Keyword {
+    fn from(s: S) -> Keyword {
+        let s = s.as_ref();
+        Keyword(Symbol::from(s.strip_prefix(':').unwrap_or(s)))
+    }
+}
+
+impl From<Symbol> for Keyword {
+    fn from(s: Symbol) -> Keyword {
+        Keyword(s)
+    }
+}
+
+fn split_namespace(s: &str) -> (Option<&str>, &str) {
+    match s.find('/') {
+        Some(i) if s != "/" => (Some(&s[..i]), &s[i + 1..]),
+        _ => (None, s),
+    }
 }
 
 #[cfg(test)]
@@ -153,8 +373,52 @@ mod tests {
         assert_eq!(s5, s6);
 
         let s7 = Symbol::from("qux");
-        let s8 = Symbol::from(s7);
+        let s8 = Symbol::from(s7.clone());
 
         assert_eq!(s7, s8);
     }
+
+    #[test]
+    fn test_symbol_threads() {
+        let names: Vec<String> = (0..1000).map(|i| format!("threads/{}", i)).collect();
+        let symbols: Vec<Vec<Symbol>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| names.iter().map(Symbol::from).collect()))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        for s in &symbols[1..] {
+            assert!(s.iter().zip(&symbols[0]).all(|(a, b)| a.addr() == b.addr()));
+        }
+        assert_ne!(Symbol::gensym(), Symbol::gensym());
+    }
+
+    #[test]
+    fn test_symbol_ord() {
+        let mut symbols: Vec<Symbol> = ["b", "a/c", "a", "a/b", "/", "b/a"]
+            .iter()
+            .map(Symbol::from)
+            .collect();
+        symbols.sort();
+        assert_eq!(symbols, ["/", "a", "b", "a/b", "a/c", "b/a"]);
+    }
+
+    fn interned(s: &str) -> bool {
+        shard(s).0.read().unwrap().names.contains(s)
+    }
+
+    #[test]
+    fn test_symbol_freed() {
+        let s1 = Symbol::from("test_symbol_freed");
+        let s2 = s1.clone();
+        drop(s1);
+        assert!(interned("test_symbol_freed"));
+        drop(s2);
+        assert!(!interned("test_symbol_freed"));
+
+        let g = Symbol::gensym();
+        let name = g.to_string();
+        drop(g);
+        assert!(!interned(&name));
+    }
 }
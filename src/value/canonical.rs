@@ -0,0 +1,451 @@
+use std::cmp::Ordering;
+use std::fmt::Write;
+
+use chrono::{SecondsFormat, Utc};
+
+use super::Value;
+
+impl Value {
+    /// Returns the canonical EDN encoding of the value: equal values have
+    /// the same encoding, byte for byte.
+    ///
+    /// The encoding is UTF-8 text following these rules:
+    ///
+    /// 1. There is no whitespace other than a single space between the
+    ///    elements of a collection, between a map key and its value, and
+    ///    after the tag of a tagged literal. There are no commas, comments or
+    ///    discards.
+    /// 2. Map entries are ordered by the canonical encoding of their keys,
+    ///    and set members by their own canonical encoding. Encodings are
+    ///    compared bytewise. Vectors and lists keep their order.
+    /// 3. Integers are written in decimal, with a `-` sign when negative and
+    ///    no leading zeros. Big integers keep their `N` suffix.
+    /// 4. Floats are written with the fewest significant decimal digits
+    ///    that read back as the same float, choosing the digits closest to
+    ///    it, and the even last digit on a tie. With these digits written as
+    ///    `d.ddd × 10^n`, floats with `-4 <= n < 16` use fixed notation with
+    ///    at least one fraction digit (`1.0`, `0.0001`, `-12.5`); the others
+    ///    use `d[.ddd]e[-]n` (`1e16`, `1.5e-7`). `-0.0` is written `0.0`,
+    ///    and the non-finite values `##NaN`, `##Inf` and `##-Inf`.
+    /// 5. Strings escape `"`, `\` and the control characters. `\n`, `\r`,
+    ///    `\t`, `\b` and `\f` use these short forms; other control
+    ///    characters use `\uXXXX` with lowercase hex digits. Every other
+    ///    character is written as is.
+    /// 6. Characters use the names `newline`, `return`, `space`, `tab`,
+    ///    `formfeed` and `backspace`. Other control and whitespace
+    ///    characters use `\uXXXX` with lowercase hex digits, the rest are
+    ///    written as is.
+    /// 7. `#inst` literals are written in UTC as
+    ///    `YYYY-MM-DDTHH:MM:SS[.fff]Z`, with no fraction if the nanoseconds
+    ///    are zero, otherwise the fewest of 3, 6 or 9 fraction digits that
+    ///    represent them exactly.
+    /// 8. `#uuid` literals are written in lowercase hyphenated form.
+    /// 9. Namespace maps are never used; keywords and symbols are written
+    ///    with their full names.
+    pub fn to_canonical_string(&self) -> String {
+        let mut out = String::new();
+        write_canonical(&mut out, self);
+        out
+    }
+
+    /// Returns the SHA-256 digest of the canonical encoding of the value.
+    ///
+    /// See [`Value::to_canonical_string`].
+    #[cfg(feature = "digest")]
+    pub fn canonical_digest(&self) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+
+        Sha256::digest(self.to_canonical_string().as_bytes()).into()
+    }
+}
+
+fn write_canonical(out: &mut String, value: &Value) {
+    match value {
+        Value::Vector(v) => write_seq(out, "[", v.iter(), "]"),
+        Value::List(v) => write_seq(out, "(", v.iter(), ")"),
+        Value::Set(s) => {
+            let mut members: Vec<String> = s.iter().map(Value::to_canonical_string).collect();
+            members.sort_unstable();
+            out.push_str("#{");
+            out.push_str(&members.join(" "));
+            out.push('}');
+        }
+        Value::Map(m) => {
+            let mut entries: Vec<(String, &Value)> =
+                m.iter().map(|(k, v)| (k.to_string(), v)).collect();
+            entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+            out.push('{');
+            for (i, (k, v)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                out.push_str(&k);
+                out.push(' ');
+                write_canonical(out, v);
+            }
+            out.push('}');
+        }
+        Value::Float(f) => write_float(out, f.0),
+        Value::Instant(i) => write!(
+            out,
+            "#inst \"{}\"",
+            i.with_timezone(&Utc)
+                .to_rfc3339_opts(SecondsFormat::AutoSi, true)
+        )
+        .unwrap(),
+        Value::Tagged(t, v) => {
+            write!(out, "#{} ", t).unwrap();
+            write_canonical(out, v);
+        }
+        // The remaining scalars only have one printed form.
+        scalar => write!(out, "{}", scalar).unwrap(),
+    }
+}
+
+fn write_seq<'a>(
+    out: &mut String,
+    open: &str,
+    items: impl Iterator<Item = &'a Value>,
+    close: &str,
+) {
+    out.push_str(open);
+    for (i, v) in items.enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        write_canonical(out, v);
+    }
+    out.push_str(close);
+}
+
+/// Writes a float following rule 4 of [`Value::to_canonical_string`].
+fn write_float(out: &mut String, x: f64) {
+    if x.is_nan() {
+        return out.push_str("##NaN");
+    } else if x.is_infinite() {
+        return out.push_str(if x > 0.0 { "##Inf" } else { "##-Inf" });
+    } else if x == 0.0 {
+        return out.push_str("0.0");
+    }
+    if x < 0.0 {
+        out.push('-');
+    }
+    let (digits, exp) = shortest_digits(x.abs());
+    let digits: String = digits.iter().map(|d| char::from(b'0' + d)).collect();
+    if (-4..16).contains(&exp) {
+        if exp < 0 {
+            out.push_str("0.");
+            out.push_str(&"0".repeat((-exp - 1) as usize));
+            out.push_str(&digits);
+        } else {
+            let int_len = exp as usize + 1;
+            if digits.len() > int_len {
+                out.push_str(&digits[..int_len]);
+                out.push('.');
+                out.push_str(&digits[int_len..]);
+            } else {
+                out.push_str(&digits);
+                out.push_str(&"0".repeat(int_len - digits.len()));
+                out.push_str(".0");
+            }
+        }
+    } else {
+        out.push_str(&digits[..1]);
+        if digits.len() > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        write!(out, "e{}", exp).unwrap();
+    }
+}
+
+/// Returns the shortest decimal digits of a positive finite float, and the
+/// exponent `n` of the float written as `d.ddd × 10^n`.
+///
+/// This is the free-format algorithm of Burger and Dybvig, "Printing
+/// Floating-Point Numbers Quickly and Accurately", in exact integer
+/// arithmetic: `r / s` is the remaining value, and `m_minus / s` and
+/// `m_plus / s` the distances to the bounds of the interval of decimals
+/// that read back as `x`.
+fn shortest_digits(x: f64) -> (Vec<u8>, i32) {
+    const HIDDEN_BIT: u64 = 1 << 52;
+    const MIN_EXP: i32 = -1074;
+
+    let bits = x.to_bits();
+    let (f, e) = match (bits >> 52) as i32 & 0x7ff {
+        0 => (bits & (HIDDEN_BIT - 1), MIN_EXP),
+        biased => ((bits & (HIDDEN_BIT - 1)) | HIDDEN_BIT, biased - 1075),
+    };
+    // Readers round to even, so the bounds themselves read back as `x` when
+    // its mantissa is even.
+    let inclusive = f % 2 == 0;
+    // Above a power of two the float below is closer than the one above.
+    let unequal_gaps = f == HIDDEN_BIT && e > MIN_EXP;
+
+    let (mut r, mut s, mut m_plus, mut m_minus);
+    if e >= 0 {
+        let gap = Big::from(1).shl(e as u32);
+        if unequal_gaps {
+            r = Big::from(f).shl(e as u32 + 2);
+            s = Big::from(4);
+            m_plus = gap.clone().shl(1);
+        } else {
+            r = Big::from(f).shl(e as u32 + 1);
+            s = Big::from(2);
+            m_plus = gap.clone();
+        }
+        m_minus = gap;
+    } else if unequal_gaps {
+        r = Big::from(f).shl(2);
+        s = Big::from(1).shl((2 - e) as u32);
+        m_plus = Big::from(2);
+        m_minus = Big::from(1);
+    } else {
+        r = Big::from(f).shl(1);
+        s = Big::from(1).shl((1 - e) as u32);
+        m_plus = Big::from(1);
+        m_minus = Big::from(1);
+    }
+
+    // Scale so that the upper bound is in [0.1, 1), starting from an
+    // estimate of the exponent that is off by at most one.
+    let mut k = (x.log10() - 1e-10).ceil() as i32;
+    if k >= 0 {
+        s = s.mul_pow10(k as u32);
+    } else {
+        r = r.mul_pow10(-k as u32);
+        m_plus = m_plus.mul_pow10(-k as u32);
+        m_minus = m_minus.mul_pow10(-k as u32);
+    }
+    let too_high = |r: &Big, m_plus: &Big, s: &Big| {
+        let high = r.add(m_plus);
+        if inclusive {
+            high >= *s
+        } else {
+            high > *s
+        }
+    };
+    while too_high(&r, &m_plus, &s) {
+        s = s.mul_small(10);
+        k += 1;
+    }
+    while !too_high(&r.mul_small(10), &m_plus.mul_small(10), &s) {
+        r = r.mul_small(10);
+        m_plus = m_plus.mul_small(10);
+        m_minus = m_minus.mul_small(10);
+        k -= 1;
+    }
+
+    let mut digits = Vec::new();
+    loop {
+        r = r.mul_small(10);
+        m_plus = m_plus.mul_small(10);
+        m_minus = m_minus.mul_small(10);
+        let mut digit = 0;
+        while r >= s {
+            r = r.sub(&s);
+            digit += 1;
+        }
+        let low = if inclusive { r <= m_minus } else { r < m_minus };
+        let high = too_high(&r, &m_plus, &s);
+        let digit = match (low, high) {
+            (false, false) => {
+                digits.push(digit);
+                continue;
+            }
+            (true, false) => digit,
+            (false, true) => digit + 1,
+            (true, true) => match r.shl(1).cmp(&s) {
+                Ordering::Less => digit,
+                Ordering::Greater => digit + 1,
+                Ordering::Equal => digit + digit % 2,
+            },
+        };
+        digits.push(digit);
+        return (digits, k - 1);
+    }
+}
+
+/// A non-negative integer with the few operations `shortest_digits` needs,
+/// in base 2^32 limbs, least significant first, without leading zero limbs.
+#[derive(Clone, PartialEq, Eq)]
+struct Big(Vec<u32>);
+
+impl From<u64> for Big {
+    fn from(n: u64) -> Big {
+        Big(vec![n as u32, (n >> 32) as u32]).trimmed()
+    }
+}
+
+impl Big {
+    fn trimmed(mut self) -> Big {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+        self
+    }
+
+    fn shl(mut self, n: u32) -> Big {
+        let bits = n % 32;
+        if bits > 0 {
+            let mut carry = 0;
+            for limb in &mut self.0 {
+                let next = *limb >> (32 - bits);
+                *limb = (*limb << bits) | carry;
+                carry = next;
+            }
+            self.0.push(carry);
+        }
+        self.0
+            .splice(0..0, std::iter::repeat_n(0, (n / 32) as usize));
+        self.trimmed()
+    }
+
+    fn mul_small(&self, m: u32) -> Big {
+        let mut out = Vec::with_capacity(self.0.len() + 1);
+        let mut carry = 0;
+        for &limb in &self.0 {
+            let t = limb as u64 * m as u64 + carry;
+            out.push(t as u32);
+            carry = t >> 32;
+        }
+        out.push(carry as u32);
+        Big(out).trimmed()
+    }
+
+    fn mul_pow10(self, n: u32) -> Big {
+        (0..n).fold(self, |b, _| b.mul_small(10))
+    }
+
+    fn add(&self, other: &Big) -> Big {
+        let len = self.0.len().max(other.0.len());
+        let mut out = Vec::with_capacity(len + 1);
+        let mut carry = 0;
+        for i in 0..len {
+            let t =
+                *self.0.get(i).unwrap_or(&0) as u64 + *other.0.get(i).unwrap_or(&0) as u64 + carry;
+            out.push(t as u32);
+            carry = t >> 32;
+        }
+        out.push(carry as u32);
+        Big(out).trimmed()
+    }
+
+    /// Subtracts `other`, which must not be larger.
+    fn sub(mut self, other: &Big) -> Big {
+        let mut borrow = 0;
+        for (i, limb) in self.0.iter_mut().enumerate() {
+            let t = *limb as i64 - *other.0.get(i).unwrap_or(&0) as i64 - borrow;
+            *limb = t.rem_euclid(1 << 32) as u32;
+            borrow = (t < 0) as i64;
+        }
+        self.trimmed()
+    }
+}
+
+impl Ord for Big {
+    fn cmp(&self, other: &Big) -> Ordering {
+        self.0
+            .len()
+            .cmp(&other.0.len())
+            .then_with(|| self.0.iter().rev().cmp(other.0.iter().rev()))
+    }
+}
+
+impl PartialOrd for Big {
+    fn partial_cmp(&self, other: &Big) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_edn;
+    use crate::Value;
+
+    fn canonical(s: &str) -> String {
+        parse_edn(s).unwrap().to_canonical_string()
+    }
+
+    #[test]
+    fn test_canonical() {
+        assert_eq!(
+            canonical("{:b #{3 \"x\" 1}, :a [1.0 -0.0 1e16 0.0001 1e-5], \"c\" (\\a \\newline)}"),
+            "{\"c\" (\\a \\newline) :a [1.0 0.0 1e16 0.0001 1e-5] :b #{\"x\" 1 3}}"
+        );
+        assert_eq!(
+            canonical("#foo #inst \"2018-01-01T02:00:00.120+02:00\""),
+            "#foo #inst \"2018-01-01T00:00:00.120Z\""
+        );
+        assert_eq!(
+            canonical("#inst \"2018-01-01T00:00:00.000Z\""),
+            "#inst \"2018-01-01T00:00:00Z\""
+        );
+        assert_eq!(
+            canonical("#uuid \"F81D4FAE-7DEC-11D0-A765-00A0C91E6BF6\""),
+            "#uuid \"f81d4fae-7dec-11d0-a765-00a0c91e6bf6\""
+        );
+        assert_eq!(canonical("{:x/a 1 :b {}}"), canonical("{:b {} :x/a 1}"));
+    }
+
+    #[test]
+    fn test_canonical_floats() {
+        let cases: &[(f64, &str)] = &[
+            (1.0, "1.0"),
+            (-0.0, "0.0"),
+            (0.1, "0.1"),
+            (-12.5, "-12.5"),
+            (100.0, "100.0"),
+            (1.0 / 3.0, "0.3333333333333333"),
+            (0.0001, "0.0001"),
+            (0.00009999, "9.999e-5"),
+            (1.5e-7, "1.5e-7"),
+            (9999999999999998.0, "9999999999999998.0"),
+            (1e16, "1e16"),
+            (1.2345678901234568e17, "1.2345678901234568e17"),
+            (1e21, "1e21"),
+            (1e23, "1e23"),
+            (2f64.powi(60), "1.152921504606847e18"),
+            // Halfway between two 17 digit decimals, the even one is used.
+            (1658206780088562.0 + 0.25, "1658206780088562.2"),
+            (f64::MAX, "1.7976931348623157e308"),
+            (f64::MIN_POSITIVE, "2.2250738585072014e-308"),
+            (5e-324, "5e-324"),
+            (f64::NAN, "##NaN"),
+            (f64::NEG_INFINITY, "##-Inf"),
+        ];
+        for (x, expected) in cases {
+            assert_eq!(Value::Float((*x).into()).to_canonical_string(), *expected);
+        }
+
+        let mut bits: u64 = 0x9e37_79b9_7f4a_7c15;
+        for _ in 0..10_000 {
+            bits ^= bits << 13;
+            bits ^= bits >> 7;
+            bits ^= bits << 17;
+            let x = f64::from_bits(bits);
+            if x.is_finite() {
+                let s = Value::Float(x.into()).to_canonical_string();
+                assert_eq!(s.parse::<f64>().unwrap(), x, "{}", s);
+            }
+        }
+    }
+
+    #[cfg(feature = "digest")]
+    #[test]
+    fn test_canonical_digest() {
+        let a = parse_edn("{:a 1, :b #{2 3}}").unwrap();
+        let b = parse_edn("{:b #{3 2} :a 1}").unwrap();
+        assert_eq!(a.canonical_digest(), b.canonical_digest());
+        let hex: String = a
+            .canonical_digest()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        // sha256("{:a 1 :b #{2 3}}")
+        assert_eq!(
+            hex,
+            "287193cb8a12610bf1a7b049e8248269f8f110d235b7103a44e6a3c591f3e5de"
+        );
+    }
+}
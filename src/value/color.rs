@@ -0,0 +1,109 @@
+//! ANSI colours for the pretty printer.
+
+use std::env;
+use std::io::{self, IsTerminal};
+
+use super::pprint::Kind;
+
+/// When to colour the output of a [`PrettyPrinter`](super::PrettyPrinter).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Colour if stdout is a terminal, `NO_COLOR` is not set and `TERM` is
+    /// not `dumb`.
+    Auto,
+    Always,
+    #[default]
+    Never,
+}
+
+impl ColorChoice {
+    pub(crate) fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                io::stdout().is_terminal()
+                    && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                    && env::var_os("TERM").is_none_or(|v| v != "dumb")
+            }
+        }
+    }
+}
+
+/// The colours of each kind of element, as ANSI SGR parameters (`"1;31"` is
+/// bold red). An empty string leaves the element uncoloured.
+///
+/// The default is close to puget's.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    /// `nil`, `true` and `false`.
+    pub constant: String,
+    pub number: String,
+    pub string: String,
+    pub character: String,
+    pub symbol: String,
+    pub keyword: String,
+    /// Tags of tagged literals, including `#inst` and `#uuid`.
+    pub tag: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            constant: "1;34".into(),
+            number: "36".into(),
+            string: "1;35".into(),
+            character: "1;35".into(),
+            symbol: String::new(),
+            keyword: "1;33".into(),
+            tag: "1;31".into(),
+        }
+    }
+}
+
+impl Theme {
+    pub(crate) fn style(&self, kind: Kind) -> &str {
+        match kind {
+            Kind::Constant => &self.constant,
+            Kind::Number => &self.number,
+            Kind::String => &self.string,
+            Kind::Character => &self.character,
+            Kind::Symbol => &self.symbol,
+            Kind::Keyword => &self.keyword,
+            Kind::Tag => &self.tag,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::parse_edn, value::PrettyPrinter};
+
+    #[test]
+    fn test_color() {
+        let v = parse_edn("[:a \"s\" 1 nil x #inst \"2020-01-01T00:00:00Z\"]").unwrap();
+        let theme = Theme {
+            keyword: "33".into(),
+            ..Theme::default()
+        };
+        let printer = PrettyPrinter::new().theme(theme);
+        assert_eq!(
+            printer.clone().print(&v),
+            "[:a \"s\" 1 nil x #inst \"2020-01-01T00:00:00.000Z\"]"
+        );
+        assert_eq!(
+            printer.color(ColorChoice::Always).print(&v),
+            "[\x1b[33m:a\x1b[0m \x1b[1;35m\"s\"\x1b[0m \x1b[36m1\x1b[0m \x1b[1;34mnil\x1b[0m x \
+             \x1b[1;31m#inst\x1b[0m \x1b[1;35m\"2020-01-01T00:00:00.000Z\"\x1b[0m]"
+        );
+    }
+
+    #[test]
+    fn test_color_width() {
+        // Escape sequences don't count towards the line width.
+        let v = parse_edn("[:aaa :bbb]").unwrap();
+        let printer = PrettyPrinter::new().width(11).color(ColorChoice::Always);
+        assert!(!printer.print(&v).contains('\n'));
+    }
+}
@@ -0,0 +1,498 @@
+//! Direct conversions between Rust values and `Value`s, without serde.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::Hash;
+
+use chrono::{DateTime, FixedOffset};
+use uuid::Uuid;
+
+use super::{Key, Value};
+use crate::error::{Error, Result};
+use crate::symbol::{Keyword, Symbol};
+
+/// Converts a Rust value to a [`Value`].
+///
+/// Implemented for the primitive types, strings, symbols, keywords, `#inst`
+/// and `#uuid` types, options, boxes, `Vec`s, sets, and maps whose keys
+/// implement [`ToEdnKey`]. With the
+/// `derive` feature, `#[derive(ToEdn)]` implements it for structs and enums,
+/// where attributes choose the EDN representation:
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// use edn::{parser::parse_edn, ToEdn};
+///
+/// #[derive(ToEdn)]
+/// #[edn(ns = "block")]
+/// struct Block {
+///     id: i64,
+///     #[edn(set)]
+///     refs: Vec<i64>,
+/// }
+///
+/// let block = Block { id: 1, refs: vec![2, 3] };
+/// assert_eq!(block.to_edn(), parse_edn("{:block/id 1 :block/refs #{2 3}}").unwrap());
+/// # }
+/// ```
+pub trait ToEdn {
+    fn to_edn(&self) -> Value;
+}
+
+/// Converts a [`Value`] to a Rust value, failing with
+/// [`Error::InvalidValue`] when it doesn't have the expected shape.
+///
+/// Sequences are read from vectors, lists and sets alike. Like [`ToEdn`],
+/// it can be derived with the `derive` feature.
+pub trait FromEdn: Sized {
+    fn from_edn(value: Value) -> Result<Self>;
+}
+
+fn expected(what: &str, found: &Value) -> Error {
+    Error::InvalidValue(format!("expected {}, found {}", what, found))
+}
+
+impl ToEdn for Value {
+    fn to_edn(&self) -> Value {
+        self.clone()
+    }
+}
+
+impl FromEdn for Value {
+    fn from_edn(value: Value) -> Result<Self> {
+        Ok(value)
+    }
+}
+
+macro_rules! convert_integer {
+    ($($ty:ident)*) => {
+        $(
+            impl ToEdn for $ty {
+                fn to_edn(&self) -> Value {
+                    Value::from(*self)
+                }
+            }
+
+            impl FromEdn for $ty {
+                fn from_edn(value: Value) -> Result<Self> {
+                    let n = match value {
+                        Value::Integer(n) => $ty::try_from(n).ok(),
+                        Value::BigInt(n) => $ty::try_from(n).ok(),
+                        v => return Err(expected("an integer", &v)),
+                    };
+                    n.ok_or_else(|| {
+                        Error::InvalidValue(format!("integer out of range for {}", stringify!($ty)))
+                    })
+                }
+            }
+        )*
+    };
+}
+
+convert_integer! {
+    i8 i16 i32 i64 i128 isize
+    u8 u16 u32 u64 usize
+}
+
+macro_rules! convert_float {
+    ($($ty:ident)*) => {
+        $(
+            impl ToEdn for $ty {
+                fn to_edn(&self) -> Value {
+                    Value::from(*self)
+                }
+            }
+
+            /// Integers are accepted too.
+            impl FromEdn for $ty {
+                fn from_edn(value: Value) -> Result<Self> {
+                    match value {
+                        Value::Float(f) => Ok(f.0 as $ty),
+                        Value::Integer(n) => Ok(n as $ty),
+                        v => Err(expected("a number", &v)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+convert_float! { f32 f64 }
+
+/// Implements the conversions of a type that a single `Value` variant
+/// holds.
+macro_rules! convert_variant {
+    ($($ty:ty => $variant:ident, $what:literal;)*) => {
+        $(
+            impl ToEdn for $ty {
+                fn to_edn(&self) -> Value {
+                    Value::$variant(self.clone())
+                }
+            }
+
+            impl FromEdn for $ty {
+                fn from_edn(value: Value) -> Result<Self> {
+                    match value {
+                        Value::$variant(v) => Ok(v),
+                        v => Err(expected($what, &v)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+convert_variant! {
+    bool => Boolean, "a boolean";
+    char => Character, "a character";
+    String => String, "a string";
+    Symbol => Symbol, "a symbol";
+    Keyword => Keyword, "a keyword";
+    Uuid => Uuid, "a UUID";
+    DateTime<FixedOffset> => Instant, "an instant";
+}
+
+impl ToEdn for str {
+    fn to_edn(&self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl ToEdn for () {
+    fn to_edn(&self) -> Value {
+        Value::Nil
+    }
+}
+
+impl FromEdn for () {
+    fn from_edn(value: Value) -> Result<Self> {
+        match value {
+            Value::Nil => Ok(()),
+            v => Err(expected("nil", &v)),
+        }
+    }
+}
+
+impl<T: ToEdn + ?Sized> ToEdn for &T {
+    fn to_edn(&self) -> Value {
+        (**self).to_edn()
+    }
+}
+
+impl<T: ToEdn + ?Sized> ToEdn for Box<T> {
+    fn to_edn(&self) -> Value {
+        (**self).to_edn()
+    }
+}
+
+impl<T: FromEdn> FromEdn for Box<T> {
+    fn from_edn(value: Value) -> Result<Self> {
+        T::from_edn(value).map(Box::new)
+    }
+}
+
+/// `None` is `nil`.
+impl<T: ToEdn> ToEdn for Option<T> {
+    fn to_edn(&self) -> Value {
+        match self {
+            Some(v) => v.to_edn(),
+            None => Value::Nil,
+        }
+    }
+}
+
+impl<T: FromEdn> FromEdn for Option<T> {
+    fn from_edn(value: Value) -> Result<Self> {
+        match value {
+            Value::Nil => Ok(None),
+            v => T::from_edn(v).map(Some),
+        }
+    }
+}
+
+/// Returns the members of a vector, list or set.
+fn elements(value: Value) -> Result<Box<dyn Iterator<Item = Value>>> {
+    match value {
+        Value::Vector(v) | Value::List(v) => Ok(Box::new(v.into_iter())),
+        Value::Set(s) => Ok(Box::new(s.into_iter())),
+        v => Err(expected("a sequence", &v)),
+    }
+}
+
+impl<T: ToEdn> ToEdn for [T] {
+    fn to_edn(&self) -> Value {
+        Value::Vector(self.iter().map(ToEdn::to_edn).collect())
+    }
+}
+
+impl<T: ToEdn> ToEdn for Vec<T> {
+    fn to_edn(&self) -> Value {
+        self.as_slice().to_edn()
+    }
+}
+
+impl<T: FromEdn> FromEdn for Vec<T> {
+    fn from_edn(value: Value) -> Result<Self> {
+        elements(value)?.map(T::from_edn).collect()
+    }
+}
+
+impl<T: ToEdn> ToEdn for BTreeSet<T> {
+    fn to_edn(&self) -> Value {
+        Value::Set(self.iter().map(ToEdn::to_edn).collect())
+    }
+}
+
+impl<T: FromEdn + Ord> FromEdn for BTreeSet<T> {
+    fn from_edn(value: Value) -> Result<Self> {
+        elements(value)?.map(T::from_edn).collect()
+    }
+}
+
+impl<T: ToEdn, S> ToEdn for HashSet<T, S> {
+    fn to_edn(&self) -> Value {
+        Value::Set(self.iter().map(ToEdn::to_edn).collect())
+    }
+}
+
+impl<T: FromEdn + Eq + Hash> FromEdn for HashSet<T> {
+    fn from_edn(value: Value) -> Result<Self> {
+        elements(value)?.map(T::from_edn).collect()
+    }
+}
+
+/// Converts a Rust value to a map [`Key`], for the keys of the maps that
+/// implement [`ToEdn`].
+///
+/// Implemented for the types that always make a keyword, string, symbol,
+/// integer, boolean, character or UUID. Integer types that may not fit in an
+/// `i64`, such as `u64`, are left out, since big integers can't be keys.
+pub trait ToEdnKey {
+    fn to_edn_key(&self) -> Key;
+}
+
+impl ToEdnKey for Key {
+    fn to_edn_key(&self) -> Key {
+        self.clone()
+    }
+}
+
+macro_rules! key_integer {
+    ($($ty:ident)*) => {
+        $(
+            impl ToEdnKey for $ty {
+                fn to_edn_key(&self) -> Key {
+                    Key::Integer(i64::from(*self))
+                }
+            }
+        )*
+    };
+}
+
+key_integer! { i8 i16 i32 i64 u8 u16 u32 }
+
+macro_rules! key_variant {
+    ($($ty:ty => $variant:ident;)*) => {
+        $(
+            impl ToEdnKey for $ty {
+                fn to_edn_key(&self) -> Key {
+                    Key::$variant(self.clone())
+                }
+            }
+        )*
+    };
+}
+
+key_variant! {
+    bool => Boolean;
+    char => Character;
+    String => String;
+    Symbol => Symbol;
+    Keyword => Keyword;
+    Uuid => Uuid;
+}
+
+impl ToEdnKey for str {
+    fn to_edn_key(&self) -> Key {
+        Key::String(self.to_string())
+    }
+}
+
+impl<T: ToEdnKey + ?Sized> ToEdnKey for &T {
+    fn to_edn_key(&self) -> Key {
+        (**self).to_edn_key()
+    }
+}
+
+impl<T: ToEdnKey + ?Sized> ToEdnKey for Box<T> {
+    fn to_edn_key(&self) -> Key {
+        (**self).to_edn_key()
+    }
+}
+
+impl<K: ToEdnKey, V: ToEdn> ToEdn for BTreeMap<K, V> {
+    fn to_edn(&self) -> Value {
+        Value::Map(
+            self.iter()
+                .map(|(k, v)| (k.to_edn_key(), v.to_edn()))
+                .collect(),
+        )
+    }
+}
+
+impl<K: FromEdn + Ord, V: FromEdn> FromEdn for BTreeMap<K, V> {
+    fn from_edn(value: Value) -> Result<Self> {
+        match value {
+            Value::Map(m) => m
+                .into_iter()
+                .map(|(k, v)| Ok((K::from_edn(k.into())?, V::from_edn(v)?)))
+                .collect(),
+            v => Err(expected("a map", &v)),
+        }
+    }
+}
+
+impl<K: ToEdnKey, V: ToEdn, S> ToEdn for HashMap<K, V, S> {
+    fn to_edn(&self) -> Value {
+        Value::Map(
+            self.iter()
+                .map(|(k, v)| (k.to_edn_key(), v.to_edn()))
+                .collect(),
+        )
+    }
+}
+
+impl<K: FromEdn + Eq + Hash, V: FromEdn> FromEdn for HashMap<K, V> {
+    fn from_edn(value: Value) -> Result<Self> {
+        match value {
+            Value::Map(m) => m
+                .into_iter()
+                .map(|(k, v)| Ok((K::from_edn(k.into())?, V::from_edn(v)?)))
+                .collect(),
+            v => Err(expected("a map", &v)),
+        }
+    }
+}
+
+/// Helpers for the code generated by `edn-derive`. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    use super::*;
+    use crate::Map;
+
+    pub fn map(value: Value) -> Result<Map<Key, Value>> {
+        match value {
+            Value::Map(m) => Ok(m),
+            v => Err(expected("a map", &v)),
+        }
+    }
+
+    /// Removes and converts the field `key` of a struct. A missing field
+    /// converts from `nil`, so that `Option`s may be left out.
+    pub fn field<T: FromEdn>(map: &mut Map<Key, Value>, key: Key) -> Result<T> {
+        match map.remove(&key) {
+            Some(v) => T::from_edn(v).map_err(|e| match e {
+                Error::InvalidValue(msg) => Error::InvalidValue(format!("{}: {}", key, msg)),
+                e => e,
+            }),
+            None => T::from_edn(Value::Nil)
+                .map_err(|_| Error::InvalidValue(format!("missing key {}", key))),
+        }
+    }
+
+    /// Returns the `N` members of a vector or list.
+    pub fn elements<const N: usize>(value: Value) -> Result<[Value; N]> {
+        let items: Vec<Value> = match value {
+            Value::Vector(v) | Value::List(v) => v.into_iter().collect(),
+            v => return Err(expected("a vector or list", &v)),
+        };
+        items
+            .try_into()
+            .map_err(|items: Vec<Value>| expected(&format!("{} elements", N), &items.into()))
+    }
+
+    pub fn nil(value: Value) -> Result<()> {
+        <()>::from_edn(value)
+    }
+
+    /// Returns the form of the tagged literal `#tag form`.
+    pub fn untag(value: Value, tag: &str) -> Result<Value> {
+        match value {
+            Value::Tagged(t, v) if t == tag => Ok(*v),
+            v => Err(expected(&format!("#{}", tag), &v)),
+        }
+    }
+
+    /// Splits an enum value into the name of its variant, `:Variant` or
+    /// `#tag`, and its data: a keyword has none, a map with a single entry
+    /// and a tagged literal have their value.
+    pub fn variant(value: Value) -> Result<(String, Option<Value>)> {
+        match value {
+            Value::Keyword(k) => Ok((k.to_string(), None)),
+            Value::Map(m) if m.len() == 1 => match m.into_iter().next() {
+                Some((Key::Keyword(k), v)) => Ok((k.to_string(), Some(v))),
+                entry => Err(expected("a keyword", &entry.expect("one entry").0.into())),
+            },
+            Value::Tagged(t, v) => Ok((format!("#{}", t), Some(*v))),
+            v => Err(expected("an enum variant", &v)),
+        }
+    }
+
+    /// Returns the data of a variant that has some.
+    pub fn data(data: Option<Value>, variant: &str) -> Result<Value> {
+        data.ok_or_else(|| Error::InvalidValue(format!("variant {} needs data", variant)))
+    }
+
+    pub fn unknown_variant(variant: &str) -> Error {
+        Error::InvalidValue(format!("unknown variant {}", variant))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_edn;
+
+    fn edn(s: &str) -> Value {
+        parse_edn(s).unwrap()
+    }
+
+    #[test]
+    fn test_conversions() {
+        let map = BTreeMap::from([("a".to_string(), vec![Some(1u64), None])]);
+        assert_eq!(map.to_edn(), edn("{\"a\" [1 nil]}"));
+        assert_eq!(BTreeMap::from_edn(map.to_edn()).unwrap(), map);
+        assert_eq!(u64::MAX.to_edn(), edn("18446744073709551615N"));
+        assert_eq!(
+            u64::from_edn(edn("18446744073709551615N")).unwrap(),
+            u64::MAX
+        );
+        assert_eq!(f64::from_edn(edn("1")).unwrap(), 1.0);
+        assert_eq!(
+            BTreeSet::<i64>::from_edn(edn("(3 1 2 1)")).unwrap(),
+            BTreeSet::from([1, 2, 3])
+        );
+        assert_eq!(
+            HashMap::<Keyword, i64>::from_edn(edn("{:a 1}")).unwrap(),
+            HashMap::from([(Keyword::from("a"), 1)])
+        );
+        assert_eq!(
+            HashMap::from([(1u8, 'a'), (2, 'b')]).to_edn(),
+            edn("{1 \\a 2 \\b}")
+        );
+        assert_eq!(
+            BTreeMap::from([(Box::<str>::from("a"), true)]).to_edn(),
+            edn("{\"a\" true}")
+        );
+
+        assert!(matches!(
+            u8::from_edn(edn("256")),
+            Err(Error::InvalidValue(_))
+        ));
+        assert_eq!(
+            Vec::<String>::from_edn(edn("[\"a\" 1]"))
+                .unwrap_err()
+                .to_string(),
+            "invalid value: expected a string, found 1"
+        );
+    }
+}
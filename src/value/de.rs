@@ -0,0 +1,856 @@
+use chrono::SecondsFormat;
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    Unexpected, VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use crate::error::{Error, Result};
+
+use super::ser::{FieldKeys, FieldNaming};
+use super::{Key, Value};
+
+/// Settings for converting [`Value`]s to Rust values through serde, the
+/// counterpart of [`SerializerOptions`](super::SerializerOptions).
+///
+/// Values map onto the serde data model as they do for `Serialize`. Enums
+/// may be given as a keyword or string for unit variants, a map with a
+/// single entry or a tagged literal, so all [`EnumStyle`](super::EnumStyle)s
+/// read back.
+///
+/// Struct fields are read from keys of the form set with
+/// [`field_keys`](Self::field_keys), or from strings. Structs inside
+/// untagged or tagged enums and structs with flattened fields are read
+/// without serde telling which struct they are, so there any key of that
+/// type is read as its name, without `:` or namespace, as written.
+/// `kebab_case` can't apply there; give those structs
+/// `#[serde(rename_all = "kebab-case")]` instead.
+///
+/// ```
+/// use edn::{parser::parse_edn, value::{DeserializerOptions, FieldKeys}};
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct User {
+///     first_name: String,
+/// }
+///
+/// let options = DeserializerOptions::new()
+///     .field_keys(FieldKeys::NamespacedKeyword)
+///     .kebab_case(true);
+/// let v = parse_edn("{:user/first-name \"Rich\"}").unwrap();
+/// assert_eq!(options.from_value::<User>(v).unwrap().first_name, "Rich");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct DeserializerOptions {
+    fields: FieldNaming,
+}
+
+/// The default options, for the `Deserializer` implementation of `Value`.
+const DEFAULT_OPTIONS: DeserializerOptions = DeserializerOptions {
+    fields: FieldNaming {
+        keys: FieldKeys::Keyword,
+        kebab_case: false,
+    },
+};
+
+impl DeserializerOptions {
+    /// Creates the default settings, which [`from_value`] uses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the type of the keys that name struct fields. Keywords by
+    /// default.
+    pub fn field_keys(mut self, keys: FieldKeys) -> Self {
+        self.fields.keys = keys;
+        self
+    }
+
+    /// Expects the names of struct fields in `kebab-case`, `:first-name`
+    /// for `first_name`.
+    pub fn kebab_case(mut self, kebab: bool) -> Self {
+        self.fields.kebab_case = kebab;
+        self
+    }
+
+    /// Converts `value` to a `T`.
+    pub fn from_value<T: DeserializeOwned>(&self, value: Value) -> Result<T> {
+        T::deserialize(Deserializer {
+            value,
+            options: self,
+        })
+    }
+}
+
+/// Converts a `Value` to a `T` with the default [`DeserializerOptions`], the
+/// inverse of [`to_value`](super::to_value).
+///
+/// ```
+/// use edn::{from_value, parser::parse_edn};
+///
+/// let v = parse_edn("[1 18446744073709551615N]").unwrap();
+/// assert_eq!(from_value::<Vec<u64>>(v).unwrap(), [1, u64::MAX]);
+/// ```
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T> {
+    DEFAULT_OPTIONS.from_value(value)
+}
+
+/// Deserializes with the default [`DeserializerOptions`].
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        Deserializer::new(self).deserialize_any(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        Deserializer::new(self).deserialize_option(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        Deserializer::new(self).deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        Deserializer::new(self).deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        Deserializer::new(self).deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map
+        identifier ignored_any
+    }
+}
+
+/// Deserializer reading a `Value`.
+struct Deserializer<'a> {
+    value: Value,
+    options: &'a DeserializerOptions,
+}
+
+impl Deserializer<'static> {
+    fn new(value: Value) -> Self {
+        Deserializer {
+            value,
+            options: &DEFAULT_OPTIONS,
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let options = self.options;
+        match self.value {
+            Value::Nil => visitor.visit_unit(),
+            Value::Boolean(b) => visitor.visit_bool(b),
+            Value::Integer(i) => visitor.visit_i64(i),
+            Value::BigInt(i) => visit_bigint(i, visitor),
+            Value::Float(f) => visitor.visit_f64(f.0),
+            Value::String(s) => visitor.visit_string(s),
+            Value::Symbol(s) => visitor.visit_str(&s),
+            Value::Keyword(k) => visitor.visit_string(k.to_string()),
+            Value::Vector(v) | Value::List(v) => visit_seq(v.into_iter(), options, visitor),
+            Value::Set(s) => visit_seq(s.into_iter(), options, visitor),
+            Value::Map(m) => visit_map(m.into_iter(), options, None, visitor),
+            Value::Instant(i) => {
+                visitor.visit_string(i.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+            }
+            Value::Uuid(u) => visitor.visit_string(u.hyphenated().to_string()),
+            Value::Character(c) => visitor.visit_char(c),
+            Value::Tagged(t, v) => visit_map(
+                [(Key::String(format!("#{}", t)), *v)].into_iter(),
+                options,
+                None,
+                visitor,
+            ),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Nil => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.value {
+            Value::Map(m) => visit_map(m.into_iter(), self.options, Some((name, fields)), visitor),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let (variant, value) = match self.value {
+            Value::Keyword(k) => (k.as_str().to_string(), None),
+            Value::String(s) => (s, None),
+            Value::Map(m) if m.len() == 1 => {
+                let (k, v) = m.into_iter().next().expect("one entry");
+                (variant_name(k)?, Some(v))
+            }
+            Value::Tagged(t, v) => (t.name().to_string(), Some(*v)),
+            v => return Err(de::Error::invalid_type(unexpected(&v), &"an enum")),
+        };
+        visitor.visit_enum(EnumDeserializer {
+            variant,
+            value,
+            options: self.options,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map
+        identifier ignored_any
+    }
+}
+
+/// Passes the smallest type holding `i`, so that visitors for narrower
+/// integers accept it when it's in their range.
+fn visit_bigint<'de, V: Visitor<'de>>(i: i128, visitor: V) -> Result<V::Value> {
+    if let Ok(i) = u64::try_from(i) {
+        visitor.visit_u64(i)
+    } else if let Ok(i) = i64::try_from(i) {
+        visitor.visit_i64(i)
+    } else if i > 0 {
+        visitor.visit_u128(i as u128)
+    } else {
+        visitor.visit_i128(i)
+    }
+}
+
+fn visit_seq<'de, I, V>(iter: I, options: &DeserializerOptions, visitor: V) -> Result<V::Value>
+where
+    I: ExactSizeIterator<Item = Value>,
+    V: Visitor<'de>,
+{
+    let len = iter.len();
+    let mut seq = SeqDeserializer { iter, options };
+    let value = visitor.visit_seq(&mut seq)?;
+    match seq.iter.len() {
+        0 => Ok(value),
+        rest => Err(de::Error::invalid_length(
+            len,
+            &&*format!("{} elements", len - rest),
+        )),
+    }
+}
+
+/// Visits the entries of a map, which holds the fields of `fields` when it
+/// is a struct.
+fn visit_map<'de, I, V>(
+    iter: I,
+    options: &DeserializerOptions,
+    fields: Option<(&str, &'static [&'static str])>,
+    visitor: V,
+) -> Result<V::Value>
+where
+    I: Iterator<Item = (Key, Value)>,
+    V: Visitor<'de>,
+{
+    visitor.visit_map(MapDeserializer {
+        iter,
+        value: None,
+        options,
+        fields,
+    })
+}
+
+fn variant_name(key: Key) -> Result<String> {
+    match key {
+        Key::Keyword(k) => Ok(k.as_str().to_string()),
+        Key::Symbol(s) => Ok(s.to_string()),
+        Key::String(s) => Ok(s),
+        k => Err(de::Error::invalid_type(unexpected_key(&k), &"a variant")),
+    }
+}
+
+fn unexpected(value: &Value) -> Unexpected<'_> {
+    match value {
+        Value::Nil => Unexpected::Unit,
+        Value::Boolean(b) => Unexpected::Bool(*b),
+        Value::Integer(i) => Unexpected::Signed(*i),
+        Value::Float(f) => Unexpected::Float(f.0),
+        Value::String(s) => Unexpected::Str(s),
+        Value::Character(c) => Unexpected::Char(*c),
+        Value::Vector(_) | Value::List(_) | Value::Set(_) => Unexpected::Seq,
+        Value::Map(_) => Unexpected::Map,
+        Value::BigInt(_) => Unexpected::Other("big integer"),
+        Value::Symbol(_) => Unexpected::Other("symbol"),
+        Value::Keyword(_) => Unexpected::Other("keyword"),
+        Value::Instant(_) => Unexpected::Other("instant"),
+        Value::Uuid(_) => Unexpected::Other("UUID"),
+        Value::Tagged(..) => Unexpected::Other("tagged literal"),
+    }
+}
+
+fn unexpected_key(key: &Key) -> Unexpected<'_> {
+    match key {
+        Key::Integer(i) => Unexpected::Signed(*i),
+        Key::Boolean(b) => Unexpected::Bool(*b),
+        Key::Character(c) => Unexpected::Char(*c),
+        Key::String(s) => Unexpected::Str(s),
+        Key::Keyword(_) => Unexpected::Other("keyword"),
+        Key::Symbol(_) => Unexpected::Other("symbol"),
+        Key::Uuid(_) => Unexpected::Other("UUID"),
+    }
+}
+
+struct SeqDeserializer<'a, I> {
+    iter: I,
+    options: &'a DeserializerOptions,
+}
+
+impl<'de, I: ExactSizeIterator<Item = Value>> SeqAccess<'de> for SeqDeserializer<'_, I> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        self.iter
+            .next()
+            .map(|value| {
+                seed.deserialize(Deserializer {
+                    value,
+                    options: self.options,
+                })
+            })
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct MapDeserializer<'a, I> {
+    iter: I,
+    value: Option<Value>,
+    options: &'a DeserializerOptions,
+    /// The name and fields of the struct being read.
+    fields: Option<(&'a str, &'static [&'static str])>,
+}
+
+impl<'de, I: Iterator<Item = (Key, Value)>> MapAccess<'de> for MapDeserializer<'_, I> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.iter.next() {
+            Some((key, v)) => {
+                self.value = Some(v);
+                let field = self
+                    .fields
+                    .and_then(|(ty, fields)| self.options.fields.field(ty, fields, &key));
+                seed.deserialize(KeyDeserializer {
+                    key,
+                    field,
+                    naming: self.fields.is_none().then_some(self.options.fields),
+                })
+                .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let value = self.value.take();
+        // Panic because this indicates a bug in the program rather than an
+        // expected failure.
+        let value = value.expect("next_value_seed called before next_key_seed");
+        seed.deserialize(Deserializer {
+            value,
+            options: self.options,
+        })
+    }
+}
+
+/// Deserializes a map key. Keys that name a field of the struct being read
+/// are identifiers for that field.
+///
+/// serde reads the maps of untagged, internally or adjacently tagged enums
+/// and of flattened fields through `deserialize_any`, without knowing the
+/// struct. There keys in the form of the field naming, if set, are given as
+/// field names, while strings keep the whole key.
+struct KeyDeserializer {
+    key: Key,
+    field: Option<&'static str>,
+    naming: Option<FieldNaming>,
+}
+
+impl KeyDeserializer {
+    fn visit_key<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.key {
+            Key::Keyword(k) => visitor.visit_string(k.to_string()),
+            Key::String(s) => visitor.visit_string(s),
+            Key::Symbol(s) => visitor.visit_str(&s),
+            Key::Integer(i) => visitor.visit_i64(i),
+            Key::Boolean(b) => visitor.visit_bool(b),
+            Key::Character(c) => visitor.visit_char(c),
+            Key::Uuid(u) => visitor.visit_string(u.hyphenated().to_string()),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if let Some(name) = self.naming.and_then(|naming| naming.any_field(&self.key)) {
+            return visitor.visit_str(name);
+        }
+        self.visit_key(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.visit_key(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.visit_key(visitor)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.field {
+            Some(field) => visitor.visit_str(field),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_enum(EnumDeserializer {
+            variant: variant_name(self.key)?,
+            value: None,
+            options: &DEFAULT_OPTIONS,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char bytes
+        byte_buf unit unit_struct seq tuple tuple_struct map struct ignored_any
+    }
+}
+
+struct EnumDeserializer<'a> {
+    variant: String,
+    value: Option<Value>,
+    options: &'a DeserializerOptions,
+}
+
+impl<'de, 'a> EnumAccess<'de> for EnumDeserializer<'a> {
+    type Error = Error;
+    type Variant = VariantDeserializer<'a>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant)> {
+        let variant = seed.deserialize(IntoDeserializer::<Error>::into_deserializer(
+            self.variant.as_str(),
+        ))?;
+        Ok((
+            variant,
+            VariantDeserializer {
+                variant: self.variant,
+                value: self.value,
+                options: self.options,
+            },
+        ))
+    }
+}
+
+/// The data of an enum variant, if it has any.
+struct VariantDeserializer<'a> {
+    variant: String,
+    value: Option<Value>,
+    options: &'a DeserializerOptions,
+}
+
+impl<'de> VariantAccess<'de> for VariantDeserializer<'_> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        match self.value {
+            None | Some(Value::Nil) => Ok(()),
+            Some(v) => Err(de::Error::invalid_type(unexpected(&v), &"a unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        match self.value {
+            Some(value) => seed.deserialize(Deserializer {
+                value,
+                options: self.options,
+            }),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"a newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Some(Value::Vector(v) | Value::List(v)) => {
+                visit_seq(v.into_iter(), self.options, visitor)
+            }
+            Some(v) => Err(de::Error::invalid_type(unexpected(&v), &"a tuple variant")),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"a tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.value {
+            Some(Value::Map(m)) => visit_map(
+                m.into_iter(),
+                self.options,
+                Some((&self.variant, fields)),
+                visitor,
+            ),
+            Some(v) => Err(de::Error::invalid_type(unexpected(&v), &"a struct variant")),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"a struct variant",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::parser::parse_edn;
+    use crate::value::{EnumStyle, FieldKeys, SerializerOptions};
+
+    fn edn(s: &str) -> Value {
+        parse_edn(s).unwrap()
+    }
+
+    #[test]
+    fn test_integers() {
+        assert_eq!(
+            from_value::<u64>(edn("18446744073709551615")).unwrap(),
+            u64::MAX
+        );
+        assert_eq!(
+            from_value::<i128>(edn("-170141183460469231731687303715884105728")).unwrap(),
+            i128::MIN
+        );
+        assert_eq!(from_value::<u8>(edn("1N")).unwrap(), 1);
+        assert!(from_value::<i64>(edn("9223372036854775808")).is_err());
+        assert!(from_value::<u8>(edn("256")).is_err());
+
+        let big = (u64::MAX, i128::MIN, i128::MAX as u128);
+        let options = SerializerOptions::new();
+        let v = options.to_value(big).unwrap();
+        assert_eq!(
+            v,
+            edn(
+                "(18446744073709551615N -170141183460469231731687303715884105728N \
+                 170141183460469231731687303715884105727N)"
+            )
+        );
+        assert_eq!(from_value::<(u64, i128, u128)>(v).unwrap(), big);
+        assert!(options.to_value(u128::MAX).is_err());
+        assert!(options.strict_integers(true).to_value(u64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+        enum E {
+            Unit,
+            Newtype(Option<u32>),
+            Tuple(i64, char),
+            Struct { a: Vec<String> },
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct S {
+            name: String,
+            count: u64,
+            items: Vec<E>,
+            keyed: BTreeMap<E, bool>,
+        }
+
+        let s = S {
+            name: "x".into(),
+            count: 3,
+            items: vec![
+                E::Unit,
+                E::Newtype(None),
+                E::Tuple(-1, 'c'),
+                E::Struct {
+                    a: vec!["a".into()],
+                },
+            ],
+            keyed: BTreeMap::from([(E::Unit, true)]),
+        };
+        for style in [EnumStyle::Map, EnumStyle::Tagged] {
+            let v = SerializerOptions::new()
+                .enum_style(style)
+                .to_value(&s)
+                .unwrap();
+            assert_eq!(from_value::<S>(v).unwrap(), s);
+        }
+        assert_eq!(
+            from_value::<S>(edn(
+                "{:name \"y\" :count 0 :items [:Unit \"Unit\"] :keyed {}}"
+            ))
+            .unwrap()
+            .items,
+            [E::Unit, E::Unit]
+        );
+    }
+
+    #[test]
+    fn test_field_keys() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        enum E {
+            Struct { a_b: i64 },
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct UserAccount {
+            user_name: String,
+            #[serde(rename = "hello world")]
+            greeting: bool,
+            e: E,
+        }
+
+        let user = UserAccount {
+            user_name: "x".into(),
+            greeting: true,
+            e: E::Struct { a_b: 1 },
+        };
+        let styles = [
+            FieldKeys::Keyword,
+            FieldKeys::NamespacedKeyword,
+            FieldKeys::String,
+            FieldKeys::Symbol,
+        ];
+        for keys in styles {
+            for kebab in [false, true] {
+                let v = SerializerOptions::new()
+                    .field_keys(keys)
+                    .kebab_case(kebab)
+                    .to_value(&user)
+                    .unwrap();
+                let options = DeserializerOptions::new()
+                    .field_keys(keys)
+                    .kebab_case(kebab);
+                assert_eq!(options.from_value::<UserAccount>(v).unwrap(), user);
+            }
+        }
+
+        // Keys of another form aren't fields.
+        let options = DeserializerOptions::new().field_keys(FieldKeys::NamespacedKeyword);
+        let v = edn("{:user-name \"x\" \"hello world\" true :user-account/e {:Struct {}}}");
+        assert!(options.from_value::<UserAccount>(v).is_err());
+        let v = edn(
+            "{\"user_name\" \"x\" \"hello world\" true :user-account/e {:Struct {:struct/a_b 1}}}",
+        );
+        assert_eq!(options.from_value::<UserAccount>(v).unwrap(), user);
+    }
+
+    #[test]
+    fn test_enum_representations() {
+        // serde reads these through `deserialize_any`, so their fields take
+        // kebab-case names themselves.
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        #[serde(tag = "type", rename_all_fields = "kebab-case")]
+        enum Internal {
+            Unit,
+            Struct { a_b: i64 },
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        #[serde(tag = "t", content = "c", rename_all_fields = "kebab-case")]
+        enum Adjacent {
+            Unit,
+            Newtype(i64),
+            Struct { a_b: i64 },
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        #[serde(untagged, rename_all_fields = "kebab-case")]
+        enum Untagged {
+            Newtype(i64),
+            Struct { a_b: i64 },
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct S {
+            internal: Vec<Internal>,
+            adjacent: Vec<Adjacent>,
+            untagged: Vec<Untagged>,
+        }
+
+        let s = S {
+            internal: vec![Internal::Unit, Internal::Struct { a_b: 1 }],
+            adjacent: vec![
+                Adjacent::Unit,
+                Adjacent::Newtype(2),
+                Adjacent::Struct { a_b: 3 },
+            ],
+            untagged: vec![Untagged::Newtype(4), Untagged::Struct { a_b: 5 }],
+        };
+        let styles = [
+            FieldKeys::Keyword,
+            FieldKeys::NamespacedKeyword,
+            FieldKeys::String,
+            FieldKeys::Symbol,
+        ];
+        for keys in styles {
+            for kebab in [false, true] {
+                let v = SerializerOptions::new()
+                    .field_keys(keys)
+                    .kebab_case(kebab)
+                    .to_value(&s)
+                    .unwrap();
+                let options = DeserializerOptions::new()
+                    .field_keys(keys)
+                    .kebab_case(kebab);
+                assert_eq!(
+                    options.from_value::<S>(v.clone()).unwrap(),
+                    s,
+                    "{:?} {}",
+                    v,
+                    kebab
+                );
+            }
+        }
+
+        // Maps with string keys get the whole key.
+        let options = DeserializerOptions::new().kebab_case(true);
+        assert_eq!(
+            options
+                .from_value::<BTreeMap<String, i64>>(edn("{:a-b 1 \"c-d\" 2}"))
+                .unwrap(),
+            BTreeMap::from([(":a-b".into(), 1), ("c-d".into(), 2)])
+        );
+    }
+
+    #[test]
+    fn test_flatten() {
+        // A field named `b_c` and one renamed to `b-c` both read back.
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Inner {
+            b_c: i64,
+            #[serde(rename = "b-c")]
+            d: i64,
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Outer {
+            a_b: i64,
+            #[serde(flatten)]
+            inner: Inner,
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        #[serde(rename_all = "kebab-case")]
+        struct KebabInner {
+            c_d: i64,
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        #[serde(rename_all = "kebab-case")]
+        struct KebabOuter {
+            a_b: i64,
+            #[serde(flatten)]
+            inner: KebabInner,
+        }
+
+        let outer = Outer {
+            a_b: 1,
+            inner: Inner { b_c: 2, d: 3 },
+        };
+        let kebab = KebabOuter {
+            a_b: 1,
+            inner: KebabInner { c_d: 2 },
+        };
+        for keys in [
+            FieldKeys::Keyword,
+            FieldKeys::NamespacedKeyword,
+            FieldKeys::String,
+            FieldKeys::Symbol,
+        ] {
+            let v = SerializerOptions::new()
+                .field_keys(keys)
+                .to_value(&outer)
+                .unwrap();
+            let options = DeserializerOptions::new().field_keys(keys);
+            assert_eq!(options.from_value::<Outer>(v).unwrap(), outer);
+
+            let v = SerializerOptions::new()
+                .field_keys(keys)
+                .kebab_case(true)
+                .to_value(&kebab)
+                .unwrap();
+            let options = DeserializerOptions::new().field_keys(keys).kebab_case(true);
+            assert_eq!(options.from_value::<KebabOuter>(v).unwrap(), kebab);
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use super::{Key, Value};
+
+/// A borrowed `Value` compared and hashed with Clojure's `=` semantics.
+///
+/// Vectors and lists holding equal elements are equal (`(= [1 2] '(1 2))`),
+/// and nested collections are compared the same way. Integers and big
+/// integers are equal when their values are (`(= 1 1N)`), but other numbers
+/// of different types never are, so `(= 1 1.0)` is false just like the
+/// derived `PartialEq`.
+///
+/// The wrapper can be used as a `HashMap`/`HashSet` key; its `Hash` impl is
+/// consistent with its `Eq` impl.
+#[derive(Clone, Copy, Debug)]
+pub struct ClojureEq<'a>(pub &'a Value);
+
+impl Value {
+    /// Compares two values with Clojure's `=` semantics.
+    ///
+    /// See [`ClojureEq`] for details.
+    pub fn clojure_eq(&self, other: &Value) -> bool {
+        equiv(self, other)
+    }
+}
+
+impl<'a> PartialEq for ClojureEq<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        equiv(self.0, other.0)
+    }
+}
+
+impl<'a> Eq for ClojureEq<'a> {}
+
+impl<'a> Hash for ClojureEq<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_equiv(self.0, state)
+    }
+}
+
+fn equiv(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Vector(a) | Value::List(a), Value::Vector(b) | Value::List(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| equiv(a, b))
+        }
+        (Value::Set(a), Value::Set(b)) => {
+            if a.len() != b.len() {
+                return false;
+            }
+            // Elements may only be equal under Clojure semantics, so the
+            // sets' own lookup can't be used. Bucket by the compatible hash,
+            // and take each matched element out so that two elements of `a`
+            // can't both match it.
+            let mut buckets: HashMap<u64, Vec<&Value>> = HashMap::new();
+            for v in b.iter() {
+                buckets.entry(equiv_hash_of(v)).or_default().push(v);
+            }
+            a.iter().all(|v| {
+                let Some(vs) = buckets.get_mut(&equiv_hash_of(v)) else {
+                    return false;
+                };
+                match vs.iter().position(|w| equiv(v, w)) {
+                    Some(i) => {
+                        vs.swap_remove(i);
+                        true
+                    }
+                    None => false,
+                }
+            })
+        }
+        (Value::Map(a), Value::Map(b)) => {
+            a.len() == b.len() && a.iter().all(|(k, v)| b.get(k).is_some_and(|w| equiv(v, w)))
+        }
+        (Value::Tagged(t1, v1), Value::Tagged(t2, v2)) => t1 == t2 && equiv(v1, v2),
+        (Value::Integer(i), Value::BigInt(n)) | (Value::BigInt(n), Value::Integer(i)) => {
+            *i as i128 == *n
+        }
+        _ => a == b,
+    }
+}
+
+fn equiv_hash_of(value: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hash_equiv(value, &mut hasher);
+    hasher.finish()
+}
+
+fn hash_equiv<H: Hasher>(value: &Value, state: &mut H) {
+    match value {
+        Value::Vector(v) | Value::List(v) => {
+            state.write_u8(7);
+            state.write_usize(v.len());
+            for v in v.iter() {
+                hash_equiv(v, state);
+            }
+        }
+        Value::Set(s) => {
+            // Order independent: combine the element hashes commutatively.
+            state.write_u8(9);
+            state.write_usize(s.len());
+            state.write_u64(
+                s.iter()
+                    .map(equiv_hash_of)
+                    .fold(0u64, |acc, h| acc.wrapping_add(h)),
+            );
+        }
+        Value::Map(m) => {
+            state.write_u8(10);
+            state.write_usize(m.len());
+            state.write_u64(
+                m.iter()
+                    .map(|(k, v)| entry_hash_of(k, v))
+                    .fold(0u64, |acc, h| acc.wrapping_add(h)),
+            );
+        }
+        Value::Tagged(t, v) => {
+            state.write_u8(14);
+            t.hash(state);
+            hash_equiv(v, state);
+        }
+        // Hash like the equal integer.
+        Value::BigInt(n) => match i64::try_from(*n) {
+            Ok(i) => Value::Integer(i).hash(state),
+            Err(_) => value.hash(state),
+        },
+        v => v.hash(state),
+    }
+}
+
+fn entry_hash_of(key: &Key, value: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hash_equiv(value, &mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::parser::parse_edn;
+
+    fn eq(a: &str, b: &str) -> bool {
+        parse_edn(a).unwrap().clojure_eq(&parse_edn(b).unwrap())
+    }
+
+    #[test]
+    fn test_clojure_eq() {
+        assert!(eq("[1 2]", "(1 2)"));
+        assert!(eq("{:a [1 (2)]}", "{:a (1 [2])}"));
+        assert!(eq("#{[1 2] :b}", "#{:b (1 2)}"));
+        assert!(eq("#foo [1]", "#foo (1)"));
+        assert!(eq("[1 -2]", "[1N -2N]"));
+        assert!(!eq("1", "1.0"));
+        assert!(!eq("[1 2]", "(1 2 3)"));
+        assert!(!eq("[1 2]", "#{1 2}"));
+        assert!(!eq("{:a [1]}", "{:a [1.0]}"));
+    }
+
+    #[test]
+    fn test_clojure_eq_sets_symmetric() {
+        // `[1]` and `(1)` are both equal to `[1]`, but only one can match it.
+        assert!(!eq("#{[1] (1)}", "#{[1] :x}"));
+        assert_eq!(eq("#{[1] (1)}", "#{[1] :x}"), eq("#{[1] :x}", "#{[1] (1)}"));
+    }
+
+    #[test]
+    fn test_clojure_eq_hash() {
+        let a = parse_edn("{:a [1 #{(2 3)}] :b ()}").unwrap();
+        let b = parse_edn("{:b [] :a (1 #{[2 3]})}").unwrap();
+
+        let set: HashSet<ClojureEq> = [ClojureEq(&a)].into_iter().collect();
+        assert!(set.contains(&ClojureEq(&b)));
+        assert_eq!(equiv_hash_of(&a), equiv_hash_of(&b));
+    }
+}
@@ -18,9 +18,27 @@ macro_rules! from_integer {
 
 from_integer! {
     i8 i16 i32 i64 isize
-    u8 u16 u32 u64 usize
+    u8 u16 u32
 }
 
+macro_rules! from_wide_integer {
+    ($($ty:ident)*) => {
+        $(
+            /// Makes a big integer if `n` doesn't fit in an `i64`.
+            impl From<$ty> for Value {
+                fn from(n: $ty) -> Self {
+                    match i64::try_from(n) {
+                        Ok(n) => Value::Integer(n),
+                        Err(_) => Value::BigInt(n as i128),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_wide_integer! { u64 usize i128 }
+
 impl From<f32> for Value {
     fn from(f: f32) -> Self {
         From::from(f as f64)
@@ -93,6 +111,20 @@ impl<K: Into<Key>, V: Into<Value>> FromIterator<(K, V)> for Value {
     }
 }
 
+impl From<Key> for Value {
+    fn from(k: Key) -> Self {
+        match k {
+            Key::Keyword(s) => Value::Keyword(s),
+            Key::String(s) => Value::String(s),
+            Key::Symbol(s) => Value::Symbol(s),
+            Key::Integer(i) => Value::Integer(i),
+            Key::Boolean(b) => Value::Boolean(b),
+            Key::Character(c) => Value::Character(c),
+            Key::Uuid(u) => Value::Uuid(u),
+        }
+    }
+}
+
 impl From<()> for Value {
     fn from((): ()) -> Self {
         Value::Nil
@@ -0,0 +1,323 @@
+//! Clojure `hasheq` compatible hashing.
+//!
+//! This is a port of `clojure.lang.Murmur3` and `clojure.lang.Util.hasheq`,
+//! so `Value::hasheq` agrees with `clojure.core/hash` on the JVM.
+
+use super::{Key, Value};
+use crate::symbol::{Keyword, Symbol};
+
+const SEED: i32 = 0;
+const C1: i32 = 0xcc9e2d51_u32 as i32;
+const C2: i32 = 0x1b873593;
+const GOLDEN_RATIO: i32 = 0x9e3779b9_u32 as i32;
+
+impl Value {
+    /// Returns the same hash as `clojure.core/hash` for the equivalent
+    /// Clojure value.
+    ///
+    /// Vectors and lists hash alike, maps and sets hash independently of
+    /// iteration order. Tagged literals have no `hasheq` in Clojure; they are
+    /// hashed as the ordered collection `[tag form]`.
+    pub fn hasheq(&self) -> i32 {
+        match self {
+            Value::Nil => 0,
+            Value::Boolean(b) => hash_bool(*b),
+            Value::Integer(i) => hash_long(*i),
+            Value::BigInt(i) => hash_bigint(*i),
+            Value::Float(f) => hash_double(f.0),
+            Value::String(s) => hash_string(s),
+            Value::Symbol(s) => hash_symbol(s),
+            Value::Keyword(s) => hash_keyword(s),
+            Value::Vector(v) | Value::List(v) => hash_ordered(v.iter().map(Value::hasheq)),
+            Value::Set(s) => hash_unordered(s.iter().map(Value::hasheq)),
+            Value::Map(m) => hash_unordered(
+                m.iter()
+                    .map(|(k, v)| hash_ordered([k.hasheq(), v.hasheq()].into_iter())),
+            ),
+            Value::Instant(i) => {
+                let t = i.timestamp_millis();
+                (t as i32) ^ ((t >> 32) as i32)
+            }
+            Value::Uuid(u) => hash_uuid(u),
+            Value::Character(c) => hash_char(*c),
+            Value::Tagged(t, v) => hash_ordered([hash_symbol(t), v.hasheq()].into_iter()),
+        }
+    }
+}
+
+impl Key {
+    /// Returns the same hash as `clojure.core/hash` for the equivalent
+    /// Clojure value. See [`Value::hasheq`].
+    pub fn hasheq(&self) -> i32 {
+        match self {
+            Key::Keyword(s) => hash_keyword(s),
+            Key::String(s) => hash_string(s),
+            Key::Symbol(s) => hash_symbol(s),
+            Key::Integer(i) => hash_long(*i),
+            Key::Boolean(b) => hash_bool(*b),
+            Key::Character(c) => hash_char(*c),
+            Key::Uuid(u) => hash_uuid(u),
+        }
+    }
+}
+
+fn hash_bigint(i: i128) -> i32 {
+    if let Ok(i) = i64::try_from(i) {
+        return hash_long(i);
+    }
+    // java.math.BigInteger#hashCode, over the 32-bit words of the magnitude
+    let magnitude = i.unsigned_abs();
+    let h = (0..4)
+        .rev()
+        .map(|word| (magnitude >> (word * 32)) as u32)
+        .skip_while(|&word| word == 0)
+        .fold(0i32, |h, word| h.wrapping_mul(31).wrapping_add(word as i32));
+    h.wrapping_mul(i.signum() as i32)
+}
+
+fn hash_bool(b: bool) -> i32 {
+    // java.lang.Boolean#hashCode
+    if b {
+        1231
+    } else {
+        1237
+    }
+}
+
+fn hash_char(c: char) -> i32 {
+    // java.lang.Character#hashCode
+    c as i32
+}
+
+fn hash_double(f: f64) -> i32 {
+    // -0.0 hashes like 0.0, everything else is java.lang.Double#hashCode
+    if f == 0.0 {
+        return 0;
+    }
+    let bits = if f.is_nan() {
+        0x7ff8000000000000
+    } else {
+        f.to_bits()
+    };
+    (bits ^ (bits >> 32)) as i32
+}
+
+fn hash_uuid(u: &uuid::Uuid) -> i32 {
+    // java.util.UUID#hashCode
+    let (msb, lsb) = u.as_u64_pair();
+    let hilo = msb ^ lsb;
+    ((hilo >> 32) as i32) ^ (hilo as i32)
+}
+
+fn hash_string(s: &str) -> i32 {
+    hash_int(string_hash_code(s))
+}
+
+fn hash_symbol(s: &Symbol) -> i32 {
+    hash_combine(
+        hash_unencoded_chars(s.name()),
+        s.namespace().map(string_hash_code).unwrap_or(0),
+    )
+}
+
+fn hash_keyword(k: &Keyword) -> i32 {
+    hash_combine(
+        hash_unencoded_chars(k.name()),
+        k.namespace().map(string_hash_code).unwrap_or(0),
+    )
+    .wrapping_add(GOLDEN_RATIO)
+}
+
+/// java.lang.String#hashCode, computed over UTF-16 code units.
+fn string_hash_code(s: &str) -> i32 {
+    s.encode_utf16()
+        .fold(0i32, |h, c| h.wrapping_mul(31).wrapping_add(c as i32))
+}
+
+fn hash_combine(seed: i32, hash: i32) -> i32 {
+    seed ^ hash
+        .wrapping_add(GOLDEN_RATIO)
+        .wrapping_add(seed << 6)
+        .wrapping_add(seed >> 2)
+}
+
+fn hash_ordered(hashes: impl Iterator<Item = i32>) -> i32 {
+    let mut n = 0;
+    let mut hash = 1i32;
+    for h in hashes {
+        hash = hash.wrapping_mul(31).wrapping_add(h);
+        n += 1;
+    }
+    mix_coll_hash(hash, n)
+}
+
+fn hash_unordered(hashes: impl Iterator<Item = i32>) -> i32 {
+    let mut n = 0;
+    let mut hash = 0i32;
+    for h in hashes {
+        hash = hash.wrapping_add(h);
+        n += 1;
+    }
+    mix_coll_hash(hash, n)
+}
+
+fn mix_coll_hash(hash: i32, count: i32) -> i32 {
+    let k1 = mix_k1(hash);
+    let h1 = mix_h1(SEED, k1);
+    fmix(h1, count)
+}
+
+fn hash_int(input: i32) -> i32 {
+    if input == 0 {
+        return 0;
+    }
+    let k1 = mix_k1(input);
+    let h1 = mix_h1(SEED, k1);
+    fmix(h1, 4)
+}
+
+fn hash_long(input: i64) -> i32 {
+    if input == 0 {
+        return 0;
+    }
+    let low = input as i32;
+    let high = ((input as u64) >> 32) as i32;
+
+    let k1 = mix_k1(low);
+    let h1 = mix_h1(SEED, k1);
+    let k1 = mix_k1(high);
+    let h1 = mix_h1(h1, k1);
+    fmix(h1, 8)
+}
+
+fn hash_unencoded_chars(s: &str) -> i32 {
+    let units: Vec<u16> = s.encode_utf16().collect();
+    let mut h1 = SEED;
+    for pair in units.chunks_exact(2) {
+        let k1 = (pair[0] as i32) | ((pair[1] as i32) << 16);
+        h1 = mix_h1(h1, mix_k1(k1));
+    }
+    if let [.., last] = units.as_slice() {
+        if units.len() % 2 == 1 {
+            h1 ^= mix_k1(*last as i32);
+        }
+    }
+    fmix(h1, 2 * units.len() as i32)
+}
+
+fn mix_k1(k1: i32) -> i32 {
+    k1.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2)
+}
+
+fn mix_h1(h1: i32, k1: i32) -> i32 {
+    (h1 ^ k1)
+        .rotate_left(13)
+        .wrapping_mul(5)
+        .wrapping_add(0xe6546b64_u32 as i32)
+}
+
+fn fmix(h1: i32, length: i32) -> i32 {
+    let mut h1 = (h1 ^ length) as u32;
+    h1 ^= h1 >> 16;
+    h1 = h1.wrapping_mul(0x85ebca6b);
+    h1 ^= h1 >> 13;
+    h1 = h1.wrapping_mul(0xc2b2ae35);
+    h1 ^= h1 >> 16;
+    h1 as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+    use std::hash::{Hash, Hasher};
+
+    use crate::parser::parse_edn;
+    use crate::value::{Key, Value};
+
+    // Expected values are `(hash x)` as computed by Clojure 1.11 on the JVM.
+    #[test]
+    fn test_hasheq() {
+        let cases = [
+            ("nil", 0),
+            ("true", 1231),
+            ("1", 1392991556),
+            ("-1", 1651860712),
+            ("42", 1871679806),
+            ("9223372036854775807", -2106506049),
+            ("1N", 1392991556),
+            ("9223372036854775808N", -2147483648),
+            ("1.5", 1073217536),
+            ("-0.0", 0),
+            ("\"\"", 0),
+            ("\"a\"", 1455541201),
+            ("\"hello\"", 1715862179),
+            ("\"é😀\"", -1453751112),
+            (":a", -2123407586),
+            (":block/uuid", -1991494552),
+            ("foo", -1385541733),
+            ("clojure.core/map", -505055043),
+            ("\\a", 97),
+            ("[]", -2017569654),
+            ("()", -2017569654),
+            ("{}", -15128758),
+            ("#{}", -15128758),
+            ("[1 2 3]", 736442005),
+            ("(1 2 3)", 736442005),
+            ("#{1 2 3}", 439094965),
+            ("{:a 1}", 1772842048),
+            ("#uuid \"f81d4fae-7dec-11d0-a765-00a0c91e6bf6\"", -343263960),
+            ("#inst \"2018-01-01T00:00:00.123Z\"", -1358655205),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_edn(input).unwrap().hasheq(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_unordered_hash() {
+        fn hash(value: &Value) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        // Sets with their own capacity and random state iterate in different
+        // orders.
+        let mut small = HashSet::new();
+        let mut large = HashSet::with_capacity(1024);
+        for i in 1..=3 {
+            small.insert(Value::Integer(i));
+            large.insert(Value::Integer(4 - i));
+        }
+        let (a, b) = (Value::Set(small), Value::Set(large));
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        assert_eq!(hash(&a), hash(&parse_edn("#{1 2 3}").unwrap()));
+        assert_eq!((a.hasheq(), b.hasheq()), (439094965, 439094965));
+
+        let entries = [
+            (Key::Keyword(":a".into()), 1),
+            (Key::Keyword(":b".into()), 2),
+        ];
+        let a = Value::Map(
+            entries
+                .iter()
+                .map(|(k, v)| (k.clone(), Value::Integer(*v)))
+                .collect(),
+        );
+        let b = Value::Map(
+            entries
+                .iter()
+                .rev()
+                .map(|(k, v)| (k.clone(), Value::Integer(*v)))
+                .collect(),
+        );
+        let parsed = parse_edn("{:b 2 :a 1}").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        assert_eq!(hash(&a), hash(&parsed));
+        assert_eq!(a.hasheq(), b.hasheq());
+        assert_eq!(a.hasheq(), parsed.hasheq());
+    }
+}
@@ -0,0 +1,187 @@
+use super::Value;
+
+/// How [`Value::deep_merge`] combines values found at the same place.
+///
+/// Maps are always merged key by key. For anything else the right-hand value
+/// wins, unless one of the options below applies.
+///
+/// With `markers` set, a value on the right can be wrapped in a tagged
+/// literal to pick the behaviour for that place only, like `^:replace` and
+/// friends in Clojure's meta-merge:
+///
+/// - `#merge/replace v` replaces the left value, even a map,
+/// - `#merge/prepend v` puts a vector or list before the left one,
+/// - `#merge/displace v` is only used if there is no left value.
+///
+/// Markers never end up in the result, wherever they are in the right-hand
+/// value, even inside vectors, lists, sets and other tagged literals.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MergeStrategy {
+    /// Appends vectors and lists to the left ones instead of replacing them.
+    pub concat: bool,
+    /// Unions sets instead of replacing them.
+    pub union: bool,
+    /// Honours `#merge/...` markers.
+    pub markers: bool,
+}
+
+impl MergeStrategy {
+    /// Merges maps, the right value wins everywhere else.
+    pub const LAST_WINS: MergeStrategy = MergeStrategy {
+        concat: false,
+        union: false,
+        markers: false,
+    };
+
+    /// Concatenates sequences, unions sets and honours markers.
+    pub const META_MERGE: MergeStrategy = MergeStrategy {
+        concat: true,
+        union: true,
+        markers: true,
+    };
+}
+
+impl Value {
+    /// Merges `other` into `self`, recursing into maps present on both
+    /// sides.
+    ///
+    /// ```
+    /// use edn::{parser::parse_edn, value::MergeStrategy};
+    ///
+    /// let base = parse_edn("{:db {:host \"localhost\" :port 5432} :tags [:a]}").unwrap();
+    /// let local = parse_edn("{:db {:port 5433} :tags [:b]}").unwrap();
+    /// assert_eq!(
+    ///     base.deep_merge(local, MergeStrategy::META_MERGE),
+    ///     parse_edn("{:db {:host \"localhost\" :port 5433} :tags [:a :b]}").unwrap()
+    /// );
+    /// ```
+    pub fn deep_merge(self, other: Value, strategy: MergeStrategy) -> Value {
+        merge(Some(self), other, strategy)
+    }
+}
+
+/// Merges `right` into `left`, which is `None` for a missing map entry.
+fn merge(left: Option<Value>, right: Value, strategy: MergeStrategy) -> Value {
+    let right = match right {
+        Value::Tagged(tag, v) if strategy.markers => match &*tag {
+            "merge/replace" => return merge(None, *v, strategy),
+            "merge/displace" => return left.unwrap_or_else(|| merge(None, *v, strategy)),
+            "merge/prepend" => {
+                let right = merge(None, *v, strategy);
+                return match (right, left) {
+                    (Value::Vector(r), Some(Value::Vector(l))) => {
+                        Value::Vector(r.into_iter().chain(l).collect())
+                    }
+                    (Value::List(r), Some(Value::List(l))) => {
+                        Value::List(r.into_iter().chain(l).collect())
+                    }
+                    (right, _) => right,
+                };
+            }
+            _ => Value::Tagged(tag, v),
+        },
+        right => right,
+    };
+
+    match (left, right) {
+        (Some(Value::Map(mut l)), Value::Map(r)) => {
+            for (k, v) in r {
+                let merged = merge(l.remove(&k), v, strategy);
+                l.insert(k, merged);
+            }
+            Value::Map(l)
+        }
+        (None, Value::Map(r)) => Value::Map(
+            r.into_iter()
+                .map(|(k, v)| (k, merge(None, v, strategy)))
+                .collect(),
+        ),
+        (Some(Value::Vector(mut l)), Value::Vector(r)) if strategy.concat => {
+            l.extend(r.into_iter().map(|v| strip_markers(v, strategy)));
+            Value::Vector(l)
+        }
+        (Some(Value::List(mut l)), Value::List(r)) if strategy.concat => {
+            l.extend(r.into_iter().map(|v| strip_markers(v, strategy)));
+            Value::List(l)
+        }
+        (Some(Value::Set(mut l)), Value::Set(r)) if strategy.union => {
+            l.extend(r.into_iter().map(|v| strip_markers(v, strategy)));
+            Value::Set(l)
+        }
+        (_, right) => strip_markers(right, strategy),
+    }
+}
+
+/// Unwraps the markers anywhere inside a right-hand value that isn't merged
+/// with anything.
+fn strip_markers(value: Value, strategy: MergeStrategy) -> Value {
+    if !strategy.markers {
+        return value;
+    }
+    value.prewalk(|mut v| loop {
+        match v {
+            Value::Tagged(tag, inner) if is_marker(&tag) => v = *inner,
+            v => return v,
+        }
+    })
+}
+
+fn is_marker(tag: &str) -> bool {
+    matches!(tag, "merge/replace" | "merge/prepend" | "merge/displace")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_edn;
+
+    fn merge(a: &str, b: &str, strategy: MergeStrategy) -> Value {
+        parse_edn(a)
+            .unwrap()
+            .deep_merge(parse_edn(b).unwrap(), strategy)
+    }
+
+    #[test]
+    fn test_deep_merge() {
+        let base = "{:a {:b 1 :c [1]} :s #{1} :l (1) :x 1}";
+        let over = "{:a {:c [2] :d 2} :s #{2} :l (2) :x {:y 1}}";
+        assert_eq!(
+            merge(base, over, MergeStrategy::LAST_WINS),
+            parse_edn("{:a {:b 1 :c [2] :d 2} :s #{2} :l (2) :x {:y 1}}").unwrap()
+        );
+        assert_eq!(
+            merge(base, over, MergeStrategy::META_MERGE),
+            parse_edn("{:a {:b 1 :c [1 2] :d 2} :s #{1 2} :l (1 2) :x {:y 1}}").unwrap()
+        );
+        assert_eq!(
+            merge("[1]", "[2]", MergeStrategy::LAST_WINS),
+            parse_edn("[2]").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_markers() {
+        let base = "{:a {:b 1} :v [1] :p [1] :d 1}";
+        let over = "{:a #merge/replace {:c 2} :v #merge/replace [2] :p #merge/prepend [0] \
+                    :d #merge/displace 2 :e #merge/displace {:f #merge/replace 3}}";
+        assert_eq!(
+            merge(base, over, MergeStrategy::META_MERGE),
+            parse_edn("{:a {:c 2} :v [2] :p [0 1] :d 1 :e {:f 3}}").unwrap()
+        );
+        // Markers nested in other values are dropped.
+        assert_eq!(
+            merge(
+                "{:v [0] :s #{0}}",
+                "{:v [#merge/replace 1 (#merge/prepend 2)] :s #{#merge/displace 3} \
+                  :t #foo #merge/replace #merge/displace {:u [#merge/replace 4]}}",
+                MergeStrategy::META_MERGE
+            ),
+            parse_edn("{:v [0 1 (2)] :s #{0 3} :t #foo {:u [4]}}").unwrap()
+        );
+        // Without markers, tagged literals are plain values.
+        assert_eq!(
+            merge("{:a 1}", "{:a #merge/replace 2}", MergeStrategy::LAST_WINS),
+            parse_edn("{:a #merge/replace 2}").unwrap()
+        );
+    }
+}
@@ -1,18 +1,39 @@
 use std::{borrow::Cow, collections::HashSet, fmt::Display};
 
-use chrono::{DateTime, FixedOffset, SecondsFormat};
+use chrono::{DateTime, FixedOffset};
 use ordered_float::OrderedFloat;
 use serde::Serialize;
 use uuid::Uuid;
 
-use self::ser::Serializer;
 use crate::error::Error;
-use crate::symbol::Symbol;
+use crate::symbol::{Keyword, Symbol};
 use crate::Map;
 
+mod canonical;
+#[cfg(feature = "color")]
+mod color;
+mod convert;
+mod de;
+mod equiv;
 mod from;
+mod hasheq;
+mod merge;
+mod pprint;
+mod print;
 mod ser;
 
+#[cfg(feature = "color")]
+pub use self::color::{ColorChoice, Theme};
+#[doc(hidden)]
+pub use self::convert::__private;
+pub use self::convert::{FromEdn, ToEdn, ToEdnKey};
+pub use self::de::{from_value, DeserializerOptions};
+pub use self::equiv::ClojureEq;
+pub use self::merge::MergeStrategy;
+pub use self::pprint::{to_string_pretty, PrettyPrinter};
+pub use self::print::{to_writer, PrinterOptions};
+pub use self::ser::{EnumStyle, FieldKeys, SerializerOptions};
+
 /// Represents any valid EDN value.
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub enum Value {
@@ -22,7 +43,7 @@ pub enum Value {
     Float(OrderedFloat<f64>),
     String(String),
     Symbol(Symbol),
-    Keyword(Symbol),
+    Keyword(Keyword),
     Vector(Vec<Value>),
     List(Vec<Value>),
     Set(HashSet<Value>),
@@ -31,72 +52,28 @@ pub enum Value {
     Uuid(Uuid),
     Character(char),
     Tagged(Symbol, Box<Value>),
+    /// A big integer, written with the `N` suffix.
+    ///
+    /// Integers outside the range of `i64` are read as big integers even
+    /// without the suffix. Unlike Clojure's, big integers hold 128 bits, so
+    /// the reader rejects integers outside the range of `i128`.
+    BigInt(i128),
 }
 
 pub fn to_value<T>(value: T) -> Result<Value, Error>
 where
     T: Serialize,
 {
-    value.serialize(Serializer)
+    SerializerOptions::new().to_value(value)
 }
 
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut options = PrinterOptions::new();
         if f.alternate() {
-            simple_pprint(f, self, 0)?;
-            return Ok(());
-        }
-        match self {
-            Value::Nil => write!(f, "nil"),
-            Value::Boolean(b) => write!(f, "{}", b),
-            Value::Integer(i) => write!(f, "{}", i),
-            Value::Float(v) => write!(f, "{}", v),
-            Value::String(s) => write!(f, "{:?}", s),
-            Value::Symbol(s) => write!(f, "{}", s),
-            Value::Keyword(s) => write!(f, "{}", s),
-            Value::Vector(v) => write!(
-                f,
-                "[{}]",
-                v.iter()
-                    .map(|v| v.to_string())
-                    .collect::<Vec<String>>()
-                    .join(" ")
-            ),
-            Value::List(v) => write!(
-                f,
-                "({})",
-                v.iter()
-                    .map(|v| v.to_string())
-                    .collect::<Vec<String>>()
-                    .join(" ")
-            ),
-            Value::Set(s) => write!(
-                f,
-                "#{{{}}}",
-                s.iter()
-                    .map(|v| v.to_string())
-                    .collect::<Vec<String>>()
-                    .join(" ")
-            ),
-            Value::Map(m) => {
-                write!(f, "{{")?;
-                for (i, (k, v)) in m.iter().enumerate() {
-                    write!(f, "{} {}", k, v)?;
-                    if i < m.len() - 1 {
-                        write!(f, ", ")?;
-                    }
-                }
-                write!(f, "}}")
-            }
-            Value::Instant(i) => write!(
-                f,
-                "#inst \"{}\"",
-                i.to_rfc3339_opts(SecondsFormat::Millis, true)
-            ),
-            Value::Uuid(u) => write!(f, "#uuid \"{}\"", u),
-            Value::Character(c) => write!(f, "\\{}", escape_character(c)),
-            Value::Tagged(t, v) => write!(f, "#{} {}", t, v),
+            options = options.pretty(PrettyPrinter::new().width(f.width().unwrap_or(80)));
         }
+        options.write(f, self)
     }
 }
 
@@ -136,18 +113,15 @@ impl std::hash::Hash for Value {
                 state.write_u8(8);
                 v.hash(state);
             }
+            // Sets and maps that compare equal may iterate in different
+            // orders, so their entries are combined order-independently.
             Value::Set(s) => {
                 state.write_u8(9);
-                for v in s {
-                    v.hash(state);
-                }
+                state.write_u64(unordered_hash(s.iter()));
             }
             Value::Map(m) => {
                 state.write_u8(10);
-                for (k, v) in m {
-                    k.hash(state);
-                    v.hash(state);
-                }
+                state.write_u64(unordered_hash(m.iter()));
             }
             Value::Instant(i) => {
                 state.write_u8(11);
@@ -166,14 +140,30 @@ impl std::hash::Hash for Value {
                 t.hash(state);
                 v.hash(state);
             }
+            Value::BigInt(i) => {
+                state.write_u8(15);
+                i.hash(state);
+            }
         }
     }
 }
 
+fn unordered_hash<T: std::hash::Hash>(items: impl Iterator<Item = T>) -> u64 {
+    use std::hash::Hasher;
+
+    items
+        .map(|item| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            item.hash(&mut hasher);
+            hasher.finish()
+        })
+        .fold(0, u64::wrapping_add)
+}
+
 /// Represents any valid EDN key.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub enum Key {
-    Keyword(Symbol),
+    Keyword(Keyword),
     String(String),
     Symbol(Symbol),
     Integer(i64),
@@ -186,7 +176,7 @@ impl Display for Key {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Key::Keyword(s) => write!(f, "{}", s),
-            Key::String(s) => write!(f, "{:?}", s),
+            Key::String(s) => write!(f, "{}", EscapedStr(s)),
             Key::Symbol(s) => write!(f, "{}", s),
             Key::Integer(i) => write!(f, "{}", i),
             Key::Boolean(b) => write!(f, "{}", b),
@@ -196,69 +186,12 @@ impl Display for Key {
     }
 }
 
-fn simple_pprint(
-    f: &mut std::fmt::Formatter,
-    value: &Value,
-    indentation: usize,
-) -> std::fmt::Result {
-    f.write_str(&" ".repeat(indentation * 2))?;
-    match value {
-        Value::Nil => writeln!(f, "nil"),
-        Value::Boolean(b) => writeln!(f, "{}", b),
-        Value::Integer(i) => writeln!(f, "{}", i),
-        Value::Float(v) => writeln!(f, "{}", v),
-        Value::String(s) => writeln!(f, "{:?}", s),
-        Value::Symbol(s) => writeln!(f, "{}", s),
-        Value::Keyword(s) => writeln!(f, "{}", s),
-        Value::Vector(val) => {
-            writeln!(f, "[")?;
-            for (i, v) in val.iter().enumerate() {
-                simple_pprint(f, v, indentation + 1)?;
-            }
-            f.write_str(&" ".repeat(indentation * 2))?;
-            writeln!(f, "]")
-        }
-        Value::List(val) => {
-            writeln!(f, "(")?;
-            for (i, v) in val.iter().enumerate() {
-                simple_pprint(f, v, indentation + 1)?;
-            }
-            f.write_str(&" ".repeat(indentation * 2))?;
-            writeln!(f, ")")
-        }
-        Value::Set(s) => {
-            writeln!(f, "#{{")?;
-            for (i, v) in s.iter().enumerate() {
-                simple_pprint(f, v, indentation + 1)?;
-            }
-            f.write_str(&" ".repeat(indentation * 2))?;
-            writeln!(f, "}}")
-        }
-        Value::Map(m) => {
-            writeln!(f, "{{")?;
-            for (i, (k, v)) in m.iter().enumerate() {
-                f.write_str(&" ".repeat(indentation * 2 + 2))?;
-                writeln!(f, "{}", k)?;
-                simple_pprint(f, v, indentation + 2)?;
-            }
-            f.write_str(&" ".repeat(indentation * 2))?;
-            writeln!(f, "}}")
-        }
-        Value::Instant(i) => writeln!(
-            f,
-            "#inst \"{}\"",
-            i.to_rfc3339_opts(SecondsFormat::Millis, true)
-        ),
-        Value::Uuid(u) => writeln!(f, "#uuid \"{}\"", u),
-        Value::Character(c) => writeln!(f, "\\{}", escape_character(c)),
-        Value::Tagged(t, v) => writeln!(f, "#{} {}", t, v),
-    }
-}
-
-// Use keyward as a default key
+/// Makes a keyword of text starting with `:`, and a symbol of any other.
+///
+/// The text isn't checked; see [`Keyword::parse`] and [`Symbol::parse`].
 impl<T: AsRef<str>> From<T> for Key {
     fn from(s: T) -> Self {
-        if s.as_ref().as_bytes()[0] == b':' {
+        if s.as_ref().starts_with(':') {
             Key::Keyword(s.as_ref().into())
         } else {
             Key::Symbol(s.as_ref().into())
@@ -283,12 +216,207 @@ impl TryFrom<Value> for Key {
     }
 }
 
+/// Prints a string as a quoted EDN string literal.
+pub(crate) struct EscapedStr<'a>(pub &'a str);
+
+impl<'a> Display for EscapedStr<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use std::fmt::Write;
+
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                '\x08' => f.write_str("\\b")?,
+                '\x0C' => f.write_str("\\f")?,
+                c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+/// Prints a float so that it reads back as the same float.
+pub(crate) struct EdnFloat(pub f64);
+
+impl Display for EdnFloat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let x = self.0;
+        if x.is_nan() {
+            f.write_str("##NaN")
+        } else if x.is_infinite() {
+            f.write_str(if x > 0.0 { "##Inf" } else { "##-Inf" })
+        } else {
+            // The shortest representation that round-trips, always with a
+            // fraction or an exponent: `1.0`, `1e100`, `1.5e-7`.
+            write!(f, "{:?}", x)
+        }
+    }
+}
+
+/// Returns the text following the backslash of a character literal.
 fn escape_character(c: &char) -> Cow<'static, str> {
     match c {
         '\n' => "newline".into(),
         '\r' => "return".into(),
         ' ' => "space".into(),
         '\t' => "tab".into(),
+        '\x0C' => "formfeed".into(),
+        '\x08' => "backspace".into(),
+        // Every control and whitespace character is in the BMP.
+        c if c.is_control() || c.is_whitespace() => format!("u{:04x}", *c as u32).into(),
         c => c.to_string().into(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::parser::parse_edn;
+
+    // Any symbol component: a letter or symbol character followed by
+    // constituents, or `-`, `+` or `.` not followed by a digit.
+    const COMPONENT: &str = "([a-zA-Z*!_?$%&=<>][a-zA-Z0-9.*+!_?$%&=<>:#-]{0,6}|\
+                             [-+.]([a-zA-Z.*+!_?$%&=<>:#-][a-zA-Z0-9.*+!_?$%&=<>:#-]{0,5})?)";
+
+    fn any_symbol() -> impl Strategy<Value = String> {
+        let regex = format!("({0}/)?{0}", COMPONENT);
+        proptest::string::string_regex(&regex)
+            .unwrap()
+            .prop_filter("nil, true and false are literals", |s| {
+                !matches!(s.as_str(), "nil" | "true" | "false")
+            })
+    }
+
+    fn any_keyword() -> impl Strategy<Value = String> {
+        let regex = format!(
+            ":[a-zA-Z0-9.*+!_?$%&=<>#-][a-zA-Z0-9.*+!_?$%&=<>:#-]{{0,5}}(/{})?",
+            COMPONENT
+        );
+        proptest::string::string_regex(&regex).unwrap()
+    }
+
+    fn any_string() -> impl Strategy<Value = String> {
+        prop::collection::vec(any::<char>(), 0..8).prop_map(String::from_iter)
+    }
+
+    fn any_key() -> impl Strategy<Value = Key> {
+        prop_oneof![
+            any_keyword().prop_map(|s| Key::Keyword(s.as_str().into())),
+            any_string().prop_map(Key::String),
+            any_symbol().prop_map(|s| Key::Symbol(s.as_str().into())),
+            any::<i64>().prop_map(Key::Integer),
+            any::<bool>().prop_map(Key::Boolean),
+            any::<char>().prop_map(Key::Character),
+            any::<u128>().prop_map(|u| Key::Uuid(Uuid::from_u128(u))),
+        ]
+    }
+
+    fn any_value() -> impl Strategy<Value = Value> {
+        let leaf = prop_oneof![
+            Just(Value::Nil),
+            any::<bool>().prop_map(Value::Boolean),
+            any::<i64>().prop_map(Value::Integer),
+            any::<f64>().prop_map(|f| Value::Float(f.into())),
+            any_string().prop_map(Value::String),
+            any_symbol().prop_map(|s| Value::Symbol(s.as_str().into())),
+            any_keyword().prop_map(|s| Value::Keyword(s.as_str().into())),
+            any::<char>().prop_map(Value::Character),
+            any::<u128>().prop_map(|u| Value::Uuid(Uuid::from_u128(u))),
+            (0..253402300799999i64).prop_map(|ms| {
+                let t = DateTime::from_timestamp_millis(ms).unwrap();
+                Value::Instant(t.fixed_offset())
+            }),
+        ];
+        leaf.prop_recursive(4, 64, 6, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..6)
+                    .prop_map(|v| Value::Vector(v.into_iter().collect())),
+                prop::collection::vec(inner.clone(), 0..6)
+                    .prop_map(|v| Value::List(v.into_iter().collect())),
+                prop::collection::vec(inner.clone(), 0..6)
+                    .prop_map(|v| Value::Set(v.into_iter().collect())),
+                prop::collection::vec((any_key(), inner.clone()), 0..6)
+                    .prop_map(|v| Value::Map(v.into_iter().collect())),
+                ("[a-z][a-z0-9-]{0,6}", inner)
+                    .prop_filter("reserved tag", |(t, _)| t != "uuid" && t != "inst")
+                    .prop_map(|(t, v)| Value::Tagged(t.as_str().into(), Box::new(v))),
+            ]
+        })
+    }
+
+    #[test]
+    fn test_escapes() {
+        let s = Value::String("\"\\\n\r\t\x08\x0C\x1b\u{7f}é😀/".into());
+        assert_eq!(s.to_string(), r#""\"\\\n\r\t\b\f\u001b\u007fé😀/""#);
+        assert_eq!(parse_edn(r#""\/""#).unwrap(), Value::String("/".into()));
+
+        let chars = ['\x0C', '\x08', '\x07', '\u{a0}', 'a', 'é'];
+        let printed = chars.map(|c| Value::Character(c).to_string());
+        assert_eq!(
+            printed,
+            [
+                "\\formfeed",
+                "\\backspace",
+                "\\u0007",
+                "\\u00a0",
+                "\\a",
+                "\\é"
+            ]
+        );
+        for (c, s) in chars.iter().zip(printed) {
+            assert_eq!(parse_edn(&s).unwrap(), Value::Character(*c));
+        }
+    }
+
+    #[test]
+    fn test_floats() {
+        let cases = [
+            (1.0, "1.0"),
+            (-0.0, "-0.0"),
+            (0.1, "0.1"),
+            (1e100, "1e100"),
+            (1.5e-7, "1.5e-7"),
+            (f64::MAX, "1.7976931348623157e308"),
+            (f64::INFINITY, "##Inf"),
+            (f64::NEG_INFINITY, "##-Inf"),
+            (f64::NAN, "##NaN"),
+        ];
+        for (f, s) in cases {
+            let v = Value::Float(f.into());
+            assert_eq!(v.to_string(), s);
+            assert_eq!(parse_edn(s).unwrap(), v);
+        }
+        assert_eq!(
+            parse_edn("[##NaN ##-Inf]").unwrap().to_string(),
+            "[##NaN ##-Inf]"
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_float_roundtrip(f in any::<f64>()) {
+            let Value::Float(g) = parse_edn(&Value::Float(f.into()).to_string()).unwrap() else {
+                panic!("not a float");
+            };
+            if f.is_nan() {
+                prop_assert!(g.is_nan());
+            } else {
+                prop_assert_eq!(f.to_bits(), g.to_bits());
+            }
+        }
+
+        #[test]
+        fn test_print_parse_roundtrip(v in any_value()) {
+            let printed = v.to_string();
+            prop_assert_eq!(parse_edn(&printed).ok(), Some(v), "{}", printed);
+        }
+    }
+}
@@ -0,0 +1,412 @@
+//! A width-aware pretty printer in the style of Wadler's "prettier printer"
+//! and fipp.
+//!
+//! A collection is printed on one line if it fits in the remaining width,
+//! otherwise its elements go on separate lines and the nested collections
+//! get the same chance. Map entries stay on one line with their key.
+
+use std::fmt::{self, Write};
+
+#[cfg(feature = "color")]
+use super::color::{ColorChoice, Theme};
+use super::{Key, PrinterOptions, Value};
+
+/// Pretty printer settings.
+///
+/// ```
+/// use edn::{parser::parse_edn, value::PrettyPrinter};
+///
+/// let v = parse_edn("{:tags [:data :format]}").unwrap();
+/// let s = PrettyPrinter::new().width(20).print(&v);
+/// assert_eq!(s, "{:tags [:data\n        :format]}");
+/// ```
+#[derive(Clone, Debug)]
+pub struct PrettyPrinter {
+    width: usize,
+    indent: Option<usize>,
+    align_map_values: bool,
+    #[cfg(feature = "color")]
+    color: ColorChoice,
+    #[cfg(feature = "color")]
+    theme: Theme,
+}
+
+impl Default for PrettyPrinter {
+    fn default() -> Self {
+        PrettyPrinter {
+            width: 80,
+            indent: None,
+            align_map_values: false,
+            #[cfg(feature = "color")]
+            color: ColorChoice::Never,
+            #[cfg(feature = "color")]
+            theme: Theme::default(),
+        }
+    }
+}
+
+impl PrettyPrinter {
+    /// Creates a printer for 80 columns, with elements aligned to the first
+    /// one.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the target line width.
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Puts the elements of a broken collection on their own lines,
+    /// indented by `indent` columns, with the closing delimiter on a line of
+    /// its own.
+    ///
+    /// By default elements are aligned with the first one, which stays on
+    /// the line of the opening delimiter.
+    pub fn indent(mut self, indent: usize) -> Self {
+        self.indent = Some(indent);
+        self
+    }
+
+    /// Pads the keys of a broken map so that its values line up.
+    pub fn align_map_values(mut self, align: bool) -> Self {
+        self.align_map_values = align;
+        self
+    }
+
+    /// Sets when to colour the output. Never by default.
+    #[cfg(feature = "color")]
+    pub fn color(mut self, color: ColorChoice) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets the colours to use.
+    #[cfg(feature = "color")]
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Pretty prints `value`.
+    pub fn print(&self, value: &Value) -> String {
+        let mut out = String::new();
+        self.write(&mut out, value)
+            .expect("writing to a String can't fail");
+        out
+    }
+
+    /// Pretty prints `value` into `out`.
+    pub fn write<W: Write>(&self, out: &mut W, value: &Value) -> fmt::Result {
+        self.write_with(out, value, &PrinterOptions::new())
+    }
+
+    /// Pretty prints `value` into `out`, with the content settings of
+    /// `options`.
+    pub(crate) fn write_with<W: Write>(
+        &self,
+        out: &mut W,
+        value: &Value,
+        options: &PrinterOptions,
+    ) -> fmt::Result {
+        let doc = self.doc(value, options);
+        #[cfg(feature = "color")]
+        if self.color.enabled() {
+            return render(out, self.width, &doc, |kind| self.theme.style(kind));
+        }
+        render(out, self.width, &doc, |_| "")
+    }
+
+    fn doc(&self, value: &Value, options: &PrinterOptions) -> Doc {
+        match value {
+            Value::Vector(v) => self.coll("[", v.iter().map(|v| self.doc(v, options)), "]"),
+            Value::List(v) => self.coll("(", v.iter().map(|v| self.doc(v, options)), ")"),
+            Value::Set(s) => {
+                let members = options.set_members(s).into_iter();
+                self.coll("#{", members.map(|v| self.doc(v, options)), "}")
+            }
+            Value::Map(m) => {
+                let (ns, entries) = options.map_entries(m);
+                let pad = if self.align_map_values {
+                    entries
+                        .iter()
+                        .map(|(_, k, _)| k.chars().count())
+                        .max()
+                        .unwrap_or(0)
+                } else {
+                    0
+                };
+                let n = entries.len();
+                let entries = entries.into_iter().enumerate().map(|(i, (key, k, v))| {
+                    let width = k.chars().count();
+                    let mut entry = vec![Doc::Styled(Kind::of_key(key), k), Doc::Text(" ".into())];
+                    if pad > width {
+                        // Padding only applies when the map is broken.
+                        entry.push(Doc::FlatAlt(" ".repeat(pad - width)));
+                    }
+                    entry.push(self.doc(v, options));
+                    if options.has_commas() && i + 1 < n {
+                        entry.push(Doc::Text(",".into()));
+                    }
+                    Doc::Concat(entry)
+                });
+                let open = match ns {
+                    Some(ns) => format!("#:{}{{", ns),
+                    None => "{".into(),
+                };
+                self.coll(&open, entries, "}")
+            }
+            Value::Tagged(t, v) => Doc::Concat(vec![
+                Doc::Styled(Kind::Tag, format!("#{}", t)),
+                Doc::Text(" ".into()),
+                self.doc(v, options),
+            ]),
+            scalar => {
+                let mut text = String::new();
+                options
+                    .write_scalar(&mut text, scalar)
+                    .expect("writing to a String can't fail");
+                match scalar {
+                    // `#inst "..."` and `#uuid "..."`
+                    Value::Instant(_) | Value::Uuid(_) => {
+                        let (tag, s) = text.split_once(' ').expect("tagged literal");
+                        Doc::Concat(vec![
+                            Doc::Styled(Kind::Tag, tag.into()),
+                            Doc::Text(" ".into()),
+                            Doc::Styled(Kind::String, s.into()),
+                        ])
+                    }
+                    _ => Doc::Styled(Kind::of_value(scalar), text),
+                }
+            }
+        }
+    }
+
+    fn coll(&self, open: &str, items: impl Iterator<Item = Doc>, close: &str) -> Doc {
+        let mut body = Vec::new();
+        for (i, item) in items.enumerate() {
+            if i > 0 {
+                body.push(Doc::Line);
+            }
+            body.push(item);
+        }
+        if body.is_empty() {
+            return Doc::Text(format!("{}{}", open, close));
+        }
+        let body = match self.indent {
+            None => Doc::Align(Box::new(Doc::Concat(body))),
+            Some(n) => {
+                body.insert(0, Doc::SoftLine);
+                Doc::Concat(vec![
+                    Doc::Nest(n, Box::new(Doc::Concat(body))),
+                    Doc::SoftLine,
+                ])
+            }
+        };
+        Doc::Group(Box::new(Doc::Concat(vec![
+            Doc::Text(open.into()),
+            body,
+            Doc::Text(close.into()),
+        ])))
+    }
+}
+
+/// Pretty prints `value` with the default [`PrettyPrinter`] settings.
+pub fn to_string_pretty(value: &Value) -> String {
+    PrettyPrinter::new().print(value)
+}
+
+/// What a piece of text is, to pick its colour.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Constant,
+    Number,
+    String,
+    Character,
+    Symbol,
+    Keyword,
+    Tag,
+}
+
+impl Kind {
+    fn of_value(value: &Value) -> Kind {
+        match value {
+            Value::Integer(_) | Value::BigInt(_) | Value::Float(_) => Kind::Number,
+            Value::String(_) => Kind::String,
+            Value::Character(_) => Kind::Character,
+            Value::Symbol(_) => Kind::Symbol,
+            Value::Keyword(_) => Kind::Keyword,
+            Value::Instant(_) | Value::Uuid(_) | Value::Tagged(..) => Kind::Tag,
+            _ => Kind::Constant,
+        }
+    }
+
+    fn of_key(key: &Key) -> Kind {
+        match key {
+            Key::Keyword(_) => Kind::Keyword,
+            Key::String(_) => Kind::String,
+            Key::Symbol(_) => Kind::Symbol,
+            Key::Integer(_) => Kind::Number,
+            Key::Boolean(_) => Kind::Constant,
+            Key::Character(_) => Kind::Character,
+            Key::Uuid(_) => Kind::Tag,
+        }
+    }
+}
+
+enum Doc {
+    Text(String),
+    /// Text that can be coloured.
+    Styled(Kind, String),
+    /// Printed when the enclosing group is broken, dropped otherwise.
+    FlatAlt(String),
+    /// A space, or a line break.
+    Line,
+    /// Nothing, or a line break.
+    SoftLine,
+    Concat(Vec<Doc>),
+    /// Printed on one line if it fits, broken otherwise.
+    Group(Box<Doc>),
+    /// Indents line breaks by a number of columns.
+    Nest(usize, Box<Doc>),
+    /// Indents line breaks to the current column.
+    Align(Box<Doc>),
+}
+
+/// Prints `doc`, colouring styled text with the SGR parameters returned by
+/// `style`.
+fn render<'s, W, F>(out: &mut W, width: usize, doc: &Doc, style: F) -> fmt::Result
+where
+    W: Write,
+    F: Fn(Kind) -> &'s str,
+{
+    // (indentation, flat, doc), processed from the end.
+    let mut stack = vec![(0, false, doc)];
+    let mut col = 0;
+    while let Some((indent, flat, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(s) => {
+                out.write_str(s)?;
+                col += s.chars().count();
+            }
+            Doc::Styled(kind, s) => {
+                match style(*kind) {
+                    "" => out.write_str(s)?,
+                    sgr => write!(out, "\x1b[{}m{}\x1b[0m", sgr, s)?,
+                }
+                col += s.chars().count();
+            }
+            Doc::FlatAlt(s) if !flat => {
+                out.write_str(s)?;
+                col += s.chars().count();
+            }
+            Doc::FlatAlt(_) => {}
+            Doc::Line | Doc::SoftLine if !flat => {
+                write!(out, "\n{:indent$}", "")?;
+                col = indent;
+            }
+            Doc::Line => {
+                out.write_char(' ')?;
+                col += 1;
+            }
+            Doc::SoftLine => {}
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|d| (indent, flat, d))),
+            Doc::Group(d) => {
+                let flat = flat || fits(width as isize - col as isize, d, &stack);
+                stack.push((indent, flat, d));
+            }
+            Doc::Nest(n, d) => stack.push((indent + n, flat, d)),
+            Doc::Align(d) => stack.push((col, flat, d)),
+        }
+    }
+    Ok(())
+}
+
+/// Checks whether `doc` printed flat, followed by the rest of the document up
+/// to the next line break, fits in `remaining` columns.
+fn fits(mut remaining: isize, doc: &Doc, rest: &[(usize, bool, &Doc)]) -> bool {
+    let mut stack = vec![(true, doc)];
+    let mut rest = rest.iter().rev();
+    while remaining >= 0 {
+        let (flat, doc) = match stack.pop() {
+            Some(next) => next,
+            None => match rest.next() {
+                Some((_, flat, doc)) => (*flat, *doc),
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(s) | Doc::Styled(_, s) => remaining -= s.chars().count() as isize,
+            Doc::FlatAlt(s) if !flat => remaining -= s.chars().count() as isize,
+            Doc::FlatAlt(_) => {}
+            Doc::Line | Doc::SoftLine if !flat => return true,
+            Doc::Line => remaining -= 1,
+            Doc::SoftLine => {}
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|d| (flat, d))),
+            Doc::Group(d) | Doc::Nest(_, d) | Doc::Align(d) => stack.push((flat, d)),
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_edn;
+
+    fn pretty(printer: PrettyPrinter, s: &str) -> String {
+        printer.print(&parse_edn(s).unwrap())
+    }
+
+    #[test]
+    fn test_pretty() {
+        let doc = "[{:b {:c \"some text\"}} [1 2 3] (f x)]";
+        assert_eq!(pretty(PrettyPrinter::new(), doc), doc);
+        assert_eq!(
+            pretty(PrettyPrinter::new().width(30), doc),
+            "[{:b {:c \"some text\"}}\n [1 2 3]\n (f x)]"
+        );
+        assert_eq!(
+            pretty(PrettyPrinter::new().width(20), doc),
+            "[{:b {:c \"some text\"}}\n [1 2 3]\n (f x)]"
+        );
+        assert_eq!(
+            pretty(PrettyPrinter::new().width(10), "[[1 2 3] [4 5]]"),
+            "[[1 2 3]\n [4 5]]"
+        );
+        assert_eq!(
+            pretty(PrettyPrinter::new().width(5), "#foo [1 2]"),
+            "#foo [1\n      2]"
+        );
+    }
+
+    #[test]
+    fn test_pretty_map() {
+        assert_eq!(
+            pretty(PrettyPrinter::new().width(13), "{10 [2 3] 1 1}"),
+            "{1 1,\n 10 [2 3]}"
+        );
+        let printer = PrettyPrinter::new().width(13).align_map_values(true);
+        assert_eq!(
+            pretty(printer.clone(), "{10 [2 3] 1 1}"),
+            "{1  1,\n 10 [2 3]}"
+        );
+        assert_eq!(pretty(printer, "{2 2 1 1}"), "{1 1, 2 2}");
+    }
+
+    #[test]
+    fn test_pretty_indent() {
+        assert_eq!(
+            pretty(
+                PrettyPrinter::new().width(13).indent(2),
+                "[1 [2 3] {:a [4 5]}]"
+            ),
+            "[\n  1\n  [2 3]\n  {:a [4 5]}\n]"
+        );
+        assert_eq!(
+            pretty(PrettyPrinter::new().width(8).indent(2), "[{:a [4 5]}]"),
+            "[\n  {\n    :a [\n      4\n      5\n    ]\n  }\n]"
+        );
+    }
+}
@@ -0,0 +1,384 @@
+//! Printing `Value`s as EDN text.
+
+use std::collections::HashSet;
+use std::fmt::{self, Write};
+use std::io;
+
+use chrono::SecondsFormat;
+
+use super::{escape_character, EdnFloat, EscapedStr, Key, PrettyPrinter, Value};
+use crate::error::{Error, Result};
+use crate::Map;
+
+/// Printer settings, used by `Display`, [`to_writer`] and the
+/// [`PrettyPrinter`].
+///
+/// The defaults print everything on one line, with commas between map
+/// entries and collections in iteration order.
+///
+/// ```
+/// use edn::{parser::parse_edn, value::PrinterOptions};
+///
+/// let v = parse_edn("{:user/name \"edn\" :user/id 1}").unwrap();
+/// let options = PrinterOptions::new()
+///     .commas(false)
+///     .sort_keys(true)
+///     .namespace_maps(true);
+/// assert_eq!(options.to_string(&v), "#:user{:id 1 :name \"edn\"}");
+/// ```
+#[derive(Clone, Debug)]
+pub struct PrinterOptions {
+    commas: bool,
+    sort_keys: bool,
+    sort_sets: bool,
+    namespace_maps: bool,
+    inst_precision: SecondsFormat,
+    trailing_newline: bool,
+    pretty: Option<PrettyPrinter>,
+}
+
+impl Default for PrinterOptions {
+    fn default() -> Self {
+        PrinterOptions {
+            commas: true,
+            sort_keys: false,
+            sort_sets: false,
+            namespace_maps: false,
+            inst_precision: SecondsFormat::Millis,
+            trailing_newline: false,
+            pretty: None,
+        }
+    }
+}
+
+impl PrinterOptions {
+    /// Creates the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Separates map entries with commas, `{:a 1, :b 2}`. On by default.
+    pub fn commas(mut self, commas: bool) -> Self {
+        self.commas = commas;
+        self
+    }
+
+    /// Prints map entries ordered by key.
+    pub fn sort_keys(mut self, sort: bool) -> Self {
+        self.sort_keys = sort;
+        self
+    }
+
+    /// Prints set members ordered by their printed form.
+    pub fn sort_sets(mut self, sort: bool) -> Self {
+        self.sort_sets = sort;
+        self
+    }
+
+    /// Prints maps whose keys are all keywords or symbols of the same
+    /// namespace as namespace maps, `#:ns{:a 1}`.
+    pub fn namespace_maps(mut self, group: bool) -> Self {
+        self.namespace_maps = group;
+        self
+    }
+
+    /// Sets the precision of the seconds in `#inst` literals. Milliseconds
+    /// by default.
+    pub fn inst_precision(mut self, precision: SecondsFormat) -> Self {
+        self.inst_precision = precision;
+        self
+    }
+
+    /// Ends the output with a newline.
+    pub fn trailing_newline(mut self, newline: bool) -> Self {
+        self.trailing_newline = newline;
+        self
+    }
+
+    /// Lays out the output with a pretty printer instead of printing it on
+    /// one line.
+    pub fn pretty(mut self, printer: PrettyPrinter) -> Self {
+        self.pretty = Some(printer);
+        self
+    }
+
+    /// Prints `value` to a string.
+    pub fn to_string(&self, value: &Value) -> String {
+        let mut out = String::new();
+        self.write(&mut out, value)
+            .expect("writing to a String can't fail");
+        out
+    }
+
+    /// Prints `value` to an `io::Write`.
+    pub fn to_writer<W: io::Write>(&self, writer: W, value: &Value) -> Result<()> {
+        let mut adapter = IoAdapter {
+            inner: writer,
+            error: None,
+        };
+        match self.write(&mut adapter, value) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(Error::Io(
+                adapter
+                    .error
+                    .unwrap_or_else(|| io::Error::other("formatter error")),
+            )),
+        }
+    }
+
+    /// Prints `value` to a `fmt::Write`.
+    pub fn write<W: Write>(&self, out: &mut W, value: &Value) -> fmt::Result {
+        match &self.pretty {
+            Some(printer) => printer.write_with(out, value, self)?,
+            None => self.write_compact(out, value)?,
+        }
+        if self.trailing_newline {
+            out.write_char('\n')?;
+        }
+        Ok(())
+    }
+
+    fn write_compact<W: Write>(&self, out: &mut W, value: &Value) -> fmt::Result {
+        match value {
+            Value::Vector(v) => self.write_seq(out, "[", v.iter(), "]"),
+            Value::List(v) => self.write_seq(out, "(", v.iter(), ")"),
+            Value::Set(s) => self.write_seq(out, "#{", self.set_members(s).into_iter(), "}"),
+            Value::Map(m) => {
+                let (ns, entries) = self.map_entries(m);
+                if let Some(ns) = ns {
+                    write!(out, "#:{}", ns)?;
+                }
+                out.write_char('{')?;
+                for (i, (_, k, v)) in entries.into_iter().enumerate() {
+                    if i > 0 {
+                        out.write_str(if self.commas { ", " } else { " " })?;
+                    }
+                    write!(out, "{} ", k)?;
+                    self.write_compact(out, v)?;
+                }
+                out.write_char('}')
+            }
+            Value::Tagged(t, v) => {
+                write!(out, "#{} ", t)?;
+                self.write_compact(out, v)
+            }
+            scalar => self.write_scalar(out, scalar),
+        }
+    }
+
+    fn write_seq<'a, W: Write>(
+        &self,
+        out: &mut W,
+        open: &str,
+        items: impl Iterator<Item = &'a Value>,
+        close: &str,
+    ) -> fmt::Result {
+        out.write_str(open)?;
+        for (i, v) in items.enumerate() {
+            if i > 0 {
+                out.write_char(' ')?;
+            }
+            self.write_compact(out, v)?;
+        }
+        out.write_str(close)
+    }
+
+    /// Prints anything but a collection or a tagged literal.
+    pub(crate) fn write_scalar<W: Write>(&self, out: &mut W, value: &Value) -> fmt::Result {
+        match value {
+            Value::Nil => out.write_str("nil"),
+            Value::Boolean(b) => write!(out, "{}", b),
+            Value::Integer(i) => write!(out, "{}", i),
+            Value::BigInt(i) => write!(out, "{}N", i),
+            Value::Float(v) => write!(out, "{}", EdnFloat(v.0)),
+            Value::String(s) => write!(out, "{}", EscapedStr(s)),
+            Value::Symbol(s) => write!(out, "{}", s),
+            Value::Keyword(s) => write!(out, "{}", s),
+            Value::Instant(i) => write!(
+                out,
+                "#inst \"{}\"",
+                i.to_rfc3339_opts(self.inst_precision, true)
+            ),
+            Value::Uuid(u) => write!(out, "#uuid \"{}\"", u),
+            Value::Character(c) => write!(out, "\\{}", escape_character(c)),
+            Value::Vector(_)
+            | Value::List(_)
+            | Value::Set(_)
+            | Value::Map(_)
+            | Value::Tagged(..) => {
+                unreachable!("not a scalar")
+            }
+        }
+    }
+
+    /// Returns whether map entries are separated by commas.
+    pub(crate) fn has_commas(&self) -> bool {
+        self.commas
+    }
+
+    /// Returns the members of a set in printing order.
+    pub(crate) fn set_members<'a>(&self, set: &'a HashSet<Value>) -> Vec<&'a Value> {
+        if !self.sort_sets {
+            return set.iter().collect();
+        }
+        let compact = PrinterOptions {
+            pretty: None,
+            trailing_newline: false,
+            ..self.clone()
+        };
+        let mut members: Vec<(String, &Value)> =
+            set.iter().map(|v| (compact.to_string(v), v)).collect();
+        members.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        members.into_iter().map(|(_, v)| v).collect()
+    }
+
+    /// Returns the common namespace of a map to print it as a namespace map,
+    /// and its entries in printing order, with the keys already printed.
+    pub(crate) fn map_entries<'a>(
+        &self,
+        map: &'a Map<Key, Value>,
+    ) -> (Option<&'a str>, Vec<(&'a Key, String, &'a Value)>) {
+        let mut entries: Vec<(&Key, &Value)> = map.iter().collect();
+        if self.sort_keys {
+            entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        }
+        let ns = if self.namespace_maps {
+            common_namespace(map)
+        } else {
+            None
+        };
+        let entries = entries
+            .into_iter()
+            .map(|(k, v)| {
+                let key = match (ns, k) {
+                    (Some(_), Key::Keyword(k)) => format!(":{}", k.name()),
+                    (Some(_), Key::Symbol(s)) => s.name().to_string(),
+                    _ => k.to_string(),
+                };
+                (k, key, v)
+            })
+            .collect();
+        (ns, entries)
+    }
+}
+
+/// Prints `value` to an `io::Write` with the default [`PrinterOptions`].
+pub fn to_writer<W: io::Write>(writer: W, value: &Value) -> Result<()> {
+    PrinterOptions::new().to_writer(writer, value)
+}
+
+fn common_namespace(map: &Map<Key, Value>) -> Option<&str> {
+    let mut common = None;
+    for key in map.keys() {
+        let ns = match key {
+            Key::Keyword(k) => k.namespace()?,
+            Key::Symbol(s) => s.namespace()?,
+            _ => return None,
+        };
+        match common {
+            None => common = Some(ns),
+            Some(common) if common != ns => return None,
+            Some(_) => {}
+        }
+    }
+    common
+}
+
+struct IoAdapter<W> {
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> Write for IoAdapter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_edn;
+
+    fn print(options: PrinterOptions, s: &str) -> String {
+        options.to_string(&parse_edn(s).unwrap())
+    }
+
+    #[test]
+    fn test_options() {
+        let doc = "{:b #{3 1 2} :a [1 2] \"c\" {:x/a 1 :x/b 2}}";
+        assert_eq!(
+            print(PrinterOptions::new().sort_keys(true).sort_sets(true), doc),
+            "{:a [1 2], :b #{1 2 3}, \"c\" {:x/a 1, :x/b 2}}"
+        );
+        assert_eq!(
+            print(
+                PrinterOptions::new()
+                    .commas(false)
+                    .sort_keys(true)
+                    .sort_sets(true)
+                    .namespace_maps(true)
+                    .trailing_newline(true),
+                doc
+            ),
+            "{:a [1 2] :b #{1 2 3} \"c\" #:x{:a 1 :b 2}}\n"
+        );
+        assert_eq!(
+            print(
+                PrinterOptions::new()
+                    .sort_keys(true)
+                    .pretty(PrettyPrinter::new().width(12).indent(2)),
+                "{:b [1 2] :a 1}"
+            ),
+            "{\n  :a 1,\n  :b [1 2]\n}"
+        );
+        // Mixed namespaces aren't grouped.
+        assert_eq!(
+            print(
+                PrinterOptions::new().namespace_maps(true).sort_keys(true),
+                "{:y/b 2 :x/a 1}"
+            ),
+            "{:x/a 1, :y/b 2}"
+        );
+    }
+
+    #[test]
+    fn test_namespace_maps() {
+        assert_eq!(
+            parse_edn("#:x{:a 1 :_/b 2 :y/c 3 d 4 \"e\" 5}").unwrap(),
+            parse_edn("{:x/a 1 :b 2 :y/c 3 x/d 4 \"e\" 5}").unwrap()
+        );
+        let options = PrinterOptions::new().namespace_maps(true);
+        assert_eq!(print(options.clone(), "{:x/a {x/b 1}}"), "#:x{:a #:x{b 1}}");
+        assert_eq!(
+            parse_edn(&print(options, "{:x/a 1 :x/b [2]}")).unwrap(),
+            parse_edn("{:x/a 1 :x/b [2]}").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_inst_precision() {
+        let inst = "#inst \"2018-01-01T00:00:00.123456Z\"";
+        assert_eq!(
+            print(PrinterOptions::new(), inst),
+            "#inst \"2018-01-01T00:00:00.123Z\""
+        );
+        assert_eq!(
+            print(
+                PrinterOptions::new().inst_precision(SecondsFormat::Micros),
+                inst
+            ),
+            inst
+        );
+    }
+
+    #[test]
+    fn test_to_writer() {
+        let mut out = Vec::new();
+        to_writer(&mut out, &parse_edn("[1 \"a\"]").unwrap()).unwrap();
+        assert_eq!(out, b"[1 \"a\"]");
+    }
+}
@@ -1,16 +1,40 @@
+use std::borrow::Cow;
 use std::str;
 
 use crate::{
     error::{Error, Result},
     value::Value,
-    Map,
+    Keyword, Map, Symbol,
 };
 
-use serde::ser::{Impossible, Serialize};
+#[cfg(not(feature = "chrono-serde"))]
+use chrono::SecondsFormat;
+use chrono::{DateTime, FixedOffset};
+use serde::ser::{Error as _, Impossible, Serialize};
+use uuid::Uuid;
 
-use super::to_value;
 use super::Key;
 
+/// Values map onto the serde data model as follows:
+///
+/// | EDN                    | serde                                   |
+/// |------------------------|-----------------------------------------|
+/// | `nil`                  | unit                                    |
+/// | booleans, integers, floats, strings, characters | the same       |
+/// | big integers           | `i128`                                  |
+/// | symbols                | newtype struct of string, `foo/bar`     |
+/// | keywords               | newtype struct of string, `:foo/bar`    |
+/// | vectors, lists, sets   | sequence                                |
+/// | maps                   | map                                     |
+/// | `#inst`                | RFC 3339 string                         |
+/// | `#uuid`                | newtype struct of hyphenated string     |
+/// | `#tag value`           | map with the single entry `"#tag"`      |
+///
+/// With the `chrono-serde` and `uuid-serde` features, instants and UUIDs use
+/// the `Serialize` implementations of chrono and uuid instead; uuid writes
+/// 16 bytes to formats that aren't human readable. The newtype structs have
+/// private names, which [`to_value`](super::to_value) reads back as the EDN
+/// type.
 impl Serialize for Value {
     #[inline]
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
@@ -21,15 +45,16 @@ impl Serialize for Value {
             Value::Nil => serializer.serialize_unit(),
             Value::Boolean(b) => serializer.serialize_bool(*b),
             Value::Integer(n) => n.serialize(serializer),
+            Value::BigInt(n) => serializer.serialize_i128(*n),
             Value::Float(n) => n.serialize(serializer),
             Value::String(s) => serializer.serialize_str(s),
             Value::Vector(v) => v.serialize(serializer),
             Value::List(v) => v.serialize(serializer),
             Value::Set(v) => v.serialize(serializer),
-            Value::Symbol(s) => s.serialize(serializer),
-            Value::Keyword(s) => s.serialize(serializer),
-            //Value::Instant(i) => i.serialize(serializer),
-            //Value::Uuid(u) => u.serialize(serializer),
+            Value::Symbol(s) => serializer.serialize_newtype_struct(SYMBOL, &**s),
+            Value::Keyword(k) => serializer.serialize_newtype_struct(KEYWORD, &Displayed(k)),
+            Value::Instant(i) => serialize_instant(i, serializer),
+            Value::Uuid(u) => serializer.serialize_newtype_struct(UUID, &SerializeUuid(u)),
             Value::Character(c) => c.serialize(serializer),
             Value::Map(m) => {
                 use serde::ser::SerializeMap;
@@ -39,11 +64,17 @@ impl Serialize for Value {
                 }
                 map.end()
             }
-            _ => unimplemented!(),
+            Value::Tagged(t, v) => {
+                use serde::ser::SerializeMap;
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(&format!("#{}", t), v)?;
+                map.end()
+            }
         }
     }
 }
 
+/// Keys are serialized like the equal [`Value`].
 impl Serialize for Key {
     #[inline]
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
@@ -52,28 +83,344 @@ impl Serialize for Key {
     {
         match self {
             Key::String(s) => serializer.serialize_str(s),
-            Key::Symbol(s) => s.serialize(serializer),
-            Key::Keyword(s) => s.serialize(serializer),
+            Key::Symbol(s) => serializer.serialize_newtype_struct(SYMBOL, &**s),
+            Key::Keyword(k) => serializer.serialize_newtype_struct(KEYWORD, &Displayed(k)),
+            Key::Integer(i) => serializer.serialize_i64(*i),
+            Key::Boolean(b) => serializer.serialize_bool(*b),
             Key::Character(c) => c.serialize(serializer),
-            _ => unimplemented!(),
+            Key::Uuid(u) => serializer.serialize_newtype_struct(UUID, &SerializeUuid(u)),
         }
     }
 }
 
+fn serialize_instant<S>(
+    i: &DateTime<FixedOffset>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: ::serde::Serializer,
+{
+    #[cfg(feature = "chrono-serde")]
+    return i.serialize(serializer);
+    #[cfg(not(feature = "chrono-serde"))]
+    serializer.collect_str(&i.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+}
+
+// Symbols, keywords and UUIDs are newtype structs with these names, which
+// other formats see through and `Serializer` turns back into the EDN type.
+const SYMBOL: &str = "$edn::private::Symbol";
+const KEYWORD: &str = "$edn::private::Keyword";
+const UUID: &str = "$edn::private::Uuid";
+
+/// Serializes with `Display`.
+struct Displayed<'a, T>(&'a T);
+
+impl<T: std::fmt::Display> Serialize for Displayed<'_, T> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: ::serde::Serializer,
+    {
+        serializer.collect_str(self.0)
+    }
+}
+
+struct SerializeUuid<'a>(&'a Uuid);
+
+impl Serialize for SerializeUuid<'_> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: ::serde::Serializer,
+    {
+        #[cfg(feature = "uuid-serde")]
+        return self.0.serialize(serializer);
+        #[cfg(not(feature = "uuid-serde"))]
+        serializer.collect_str(&self.0.hyphenated())
+    }
+}
+
+/// Returns the key that the newtype struct `name` wraps, for the names
+/// above. Our serializers are human readable, so the data is a string.
+fn marked_key(name: &str, data: &str) -> Option<Key> {
+    match name {
+        SYMBOL => Symbol::parse(data).ok().map(Key::Symbol),
+        KEYWORD => Keyword::parse(data).ok().map(Key::Keyword),
+        UUID => Uuid::try_parse(data).ok().map(Key::Uuid),
+        _ => None,
+    }
+}
+
+/// How [`SerializerOptions::to_value`] represents the variants of Rust
+/// enums. Unit variants are always keywords, `:Variant`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EnumStyle {
+    /// A map with a single entry, `{:Variant data}`.
+    #[default]
+    Map,
+    /// A tagged literal with the enum's name as namespace,
+    /// `#Enum/Variant data`.
+    Tagged,
+}
+
+/// How struct fields are named in maps, set with
+/// [`SerializerOptions::field_keys`] and
+/// [`DeserializerOptions::field_keys`](super::DeserializerOptions::field_keys).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FieldKeys {
+    /// Keywords, `{:name "x"}`.
+    #[default]
+    Keyword,
+    /// Keywords with the name of the struct in kebab-case as namespace,
+    /// `{:user/name "x"}` for `struct User`. The fields of struct variants
+    /// take the name of the variant.
+    NamespacedKeyword,
+    /// Strings, `{"name" "x"}`.
+    String,
+    /// Symbols, `{name "x"}`.
+    Symbol,
+}
+
+/// The field naming settings shared by the serializer and the deserializer.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct FieldNaming {
+    pub(crate) keys: FieldKeys,
+    pub(crate) kebab_case: bool,
+}
+
+impl FieldNaming {
+    fn name<'f>(&self, field: &'f str) -> Cow<'f, str> {
+        if self.kebab_case {
+            Cow::Owned(kebab_case(field))
+        } else {
+            Cow::Borrowed(field)
+        }
+    }
+
+    /// Returns the key of `field` in the map of the struct `ty`, or of an
+    /// unknown struct if `ty` is empty, which gets no namespace. Names that
+    /// aren't valid keywords or symbols are strings, so that the output
+    /// reads back.
+    pub(crate) fn key(&self, ty: &str, field: &str) -> Key {
+        let name = self.name(field);
+        let key = match self.keys {
+            FieldKeys::String => return Key::String(name.into_owned()),
+            FieldKeys::Keyword if is_identifier(&name) => {
+                return Key::Keyword(Keyword::new(None, &name))
+            }
+            FieldKeys::Keyword => Keyword::parse(&name).map(Key::Keyword),
+            FieldKeys::NamespacedKeyword if ty.is_empty() => {
+                Keyword::parse(&name).map(Key::Keyword)
+            }
+            FieldKeys::NamespacedKeyword => {
+                Keyword::parse(&format!("{}/{}", kebab_case(ty), name)).map(Key::Keyword)
+            }
+            FieldKeys::Symbol => Symbol::parse(&name).map(Key::Symbol),
+        };
+        key.unwrap_or_else(|_| Key::String(name.into_owned()))
+    }
+
+    /// Returns the field of the struct `ty` that `key` names, the inverse of
+    /// [`key`](Self::key). String keys are accepted in every style.
+    pub(crate) fn field(
+        &self,
+        ty: &str,
+        fields: &[&'static str],
+        key: &Key,
+    ) -> Option<&'static str> {
+        let name = match (self.keys, key) {
+            (FieldKeys::Keyword, Key::Keyword(k)) if k.namespace().is_none() => k.name(),
+            (FieldKeys::NamespacedKeyword, Key::Keyword(k))
+                if k.namespace() == Some(&kebab_case(ty)) =>
+            {
+                k.name()
+            }
+            (FieldKeys::Symbol, Key::Symbol(s)) if s.namespace().is_none() => s.name(),
+            (_, Key::String(s)) => s,
+            _ => return None,
+        };
+        fields.iter().copied().find(|f| self.name(f) == name)
+    }
+
+    /// Returns the name that `key` gives when the struct isn't known, as
+    /// when serde reads it through `deserialize_any`: keys of the set type
+    /// lose their `:` and namespace. Without the fields to match against,
+    /// the name is left as written.
+    pub(crate) fn any_field<'k>(&self, key: &'k Key) -> Option<&'k str> {
+        match (self.keys, key) {
+            (FieldKeys::Keyword, Key::Keyword(k)) if k.namespace().is_none() => Some(k.name()),
+            (FieldKeys::NamespacedKeyword, Key::Keyword(k)) => Some(k.name()),
+            (FieldKeys::Symbol, Key::Symbol(s)) if s.namespace().is_none() => Some(s.name()),
+            _ => None,
+        }
+    }
+}
+
+/// Converts `CamelCase` and `snake_case` names to `kebab-case`.
+fn kebab_case(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    let mut word = false;
+    for c in s.chars() {
+        if c == '_' {
+            out.push('-');
+            word = false;
+        } else if c.is_uppercase() {
+            if word {
+                out.push('-');
+            }
+            out.extend(c.to_lowercase());
+            word = false;
+        } else {
+            out.push(c);
+            word = c.is_alphanumeric();
+        }
+    }
+    out
+}
+
+/// Whether `name` is made of ASCII letters, digits, `_` and `-` and starts
+/// with a letter, which makes it a valid keyword name without running the
+/// parser.
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Settings for converting Rust values to [`Value`]s through serde.
+///
+/// Newtype and tuple variants carry their value and a list of their values;
+/// struct variants carry a map. Struct fields are keywords unless set
+/// otherwise with [`field_keys`](Self::field_keys), including the fields of
+/// structs with `#[serde(flatten)]`. The keys of other maps are converted
+/// like values, so
+/// Rust strings are EDN strings; only symbol, keyword and UUID [`Value`]s
+/// and [`Key`]s become keys of those types.
+///
+/// ```
+/// use edn::{parser::parse_edn, value::{EnumStyle, SerializerOptions}};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// enum Shape {
+///     Point,
+///     Circle(f64),
+///     Rect { w: i64, h: i64 },
+/// }
+///
+/// let options = SerializerOptions::new();
+/// assert_eq!(options.to_value(Shape::Point).unwrap(), parse_edn(":Point").unwrap());
+/// assert_eq!(
+///     options.to_value(Shape::Rect { w: 1, h: 2 }).unwrap(),
+///     parse_edn("{:Rect {:w 1 :h 2}}").unwrap()
+/// );
+/// let options = options.enum_style(EnumStyle::Tagged);
+/// assert_eq!(
+///     options.to_value(Shape::Circle(1.5)).unwrap(),
+///     parse_edn("#Shape/Circle 1.5").unwrap()
+/// );
+/// ```
+///
+/// ```
+/// use edn::{parser::parse_edn, value::{FieldKeys, SerializerOptions}};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     first_name: String,
+/// }
+///
+/// let options = SerializerOptions::new()
+///     .field_keys(FieldKeys::NamespacedKeyword)
+///     .kebab_case(true);
+/// assert_eq!(
+///     options.to_value(User { first_name: "Rich".into() }).unwrap(),
+///     parse_edn("{:user/first-name \"Rich\"}").unwrap()
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SerializerOptions {
+    enum_style: EnumStyle,
+    strict_integers: bool,
+    fields: FieldNaming,
+}
+
+impl SerializerOptions {
+    /// Creates the default settings, which [`to_value`] uses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how enum variants are represented.
+    pub fn enum_style(mut self, style: EnumStyle) -> Self {
+        self.enum_style = style;
+        self
+    }
+
+    /// Returns an error for integers outside the range of `i64`, instead of
+    /// making them [big integers](Value::BigInt).
+    pub fn strict_integers(mut self, strict: bool) -> Self {
+        self.strict_integers = strict;
+        self
+    }
+
+    /// Sets the type of the keys that name struct fields. Keywords by
+    /// default.
+    pub fn field_keys(mut self, keys: FieldKeys) -> Self {
+        self.fields.keys = keys;
+        self
+    }
+
+    /// Converts the names of struct fields from `snake_case` to
+    /// `kebab-case`, `:first-name` for `first_name`.
+    pub fn kebab_case(mut self, kebab: bool) -> Self {
+        self.fields.kebab_case = kebab;
+        self
+    }
+
+    /// Converts `value` to a [`Value`].
+    pub fn to_value<T: Serialize>(&self, value: T) -> Result<Value> {
+        value.serialize(Serializer { options: self })
+    }
+}
+
 /// Serializer whose output is a `Value`.
-pub struct Serializer;
+#[derive(Clone, Copy)]
+pub struct Serializer<'a> {
+    options: &'a SerializerOptions,
+}
 
-impl serde::Serializer for Serializer {
+impl Serializer<'_> {
+    /// Wraps the data of a variant. Names that wouldn't read back as the
+    /// keyword or the tag are an error.
+    fn variant(self, name: &'static str, variant: &'static str, data: Value) -> Result<Value> {
+        match self.options.enum_style {
+            EnumStyle::Map => {
+                let key = Keyword::parse(variant).map_err(|_| {
+                    Error::InvalidKey(format!("`{}` isn't a valid keyword", variant))
+                })?;
+                Ok(Value::Map(Map::from_iter([(Key::Keyword(key), data)])))
+            }
+            EnumStyle::Tagged => {
+                let tag = format!("{}/{}", name, variant);
+                let tag = Symbol::parse(&tag)
+                    .map_err(|_| Error::custom(format!("`{}` isn't a valid tag", tag)))?;
+                Ok(Value::Tagged(tag, Box::new(data)))
+            }
+        }
+    }
+}
+
+impl<'a> serde::Serializer for Serializer<'a> {
     type Ok = Value;
     type Error = Error;
 
-    type SerializeSeq = SerializeVec;
-    type SerializeTuple = SerializeVec;
-    type SerializeTupleStruct = SerializeVec;
-    type SerializeTupleVariant = Impossible<Value, Error>;
-    type SerializeMap = SerializeMap;
-    type SerializeStruct = SerializeMap;
-    type SerializeStructVariant = Impossible<Value, Error>;
+    type SerializeSeq = SerializeVec<'a>;
+    type SerializeTuple = SerializeVec<'a>;
+    type SerializeTupleStruct = SerializeVec<'a>;
+    type SerializeTupleVariant = SerializeVariant<SerializeVec<'a>>;
+    type SerializeMap = SerializeMap<'a>;
+    type SerializeStruct = SerializeMap<'a>;
+    type SerializeStructVariant = SerializeVariant<SerializeMap<'a>>;
 
     #[inline]
     fn serialize_bool(self, value: bool) -> Result<Value> {
@@ -111,9 +458,28 @@ impl serde::Serializer for Serializer {
         self.serialize_i64(value as i64)
     }
 
-    // FIXME: u64 -> i64
     fn serialize_u64(self, value: u64) -> Result<Value> {
-        Ok(Value::Integer(value as i64))
+        self.serialize_i128(value as i128)
+    }
+
+    fn serialize_i128(self, value: i128) -> Result<Value> {
+        match i64::try_from(value) {
+            Ok(i) => Ok(Value::Integer(i)),
+            Err(_) if self.options.strict_integers => {
+                Err(Error::Message(format!("integer {} is out of range", value)))
+            }
+            Err(_) => Ok(Value::BigInt(value)),
+        }
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<Value> {
+        match i128::try_from(value) {
+            Ok(i) => self.serialize_i128(i),
+            Err(_) => Err(Error::Message(format!(
+                "integer {} is outside the range of 128-bit big integers",
+                value
+            ))),
+        }
     }
 
     fn serialize_f32(self, value: f32) -> Result<Value> {
@@ -151,43 +517,49 @@ impl serde::Serializer for Serializer {
         Ok(Value::Nil)
     }
 
-    fn serialize_unit_struct(self, name: &'static str) -> Result<Value> {
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value> {
         self.serialize_unit()
     }
 
     fn serialize_unit_variant(
         self,
-        name: &'static str,
-        variant_index: u32,
+        _name: &'static str,
+        _variant_index: u32,
         variant: &'static str,
     ) -> Result<Value> {
-        todo!()
+        Keyword::parse(variant)
+            .map(Value::Keyword)
+            .map_err(|_| Error::custom(format!("`{}` isn't a valid keyword", variant)))
     }
 
     fn serialize_newtype_struct<T: ?Sized>(self, name: &'static str, value: &T) -> Result<Value>
     where
         T: Serialize,
     {
-        todo!()
+        match value.serialize(self)? {
+            Value::String(s) => Ok(marked_key(name, &s).map_or(Value::String(s), Value::from)),
+            value => Ok(value),
+        }
     }
 
     fn serialize_newtype_variant<T: ?Sized>(
         self,
         name: &'static str,
-        variant_index: u32,
+        _variant_index: u32,
         variant: &'static str,
         value: &T,
     ) -> Result<Value>
     where
         T: Serialize,
     {
-        todo!()
+        self.variant(name, variant, value.serialize(self)?)
     }
 
     // serialization of compound types.
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
         Ok(SerializeVec {
+            serializer: self,
             vec: Vec::with_capacity(len.unwrap_or(0)),
             as_list: false,
         })
@@ -195,6 +567,7 @@ impl serde::Serializer for Serializer {
 
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
         Ok(SerializeVec {
+            serializer: self,
             vec: Vec::with_capacity(len),
             as_list: true,
         })
@@ -203,30 +576,41 @@ impl serde::Serializer for Serializer {
     fn serialize_tuple_struct(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        todo!()
+        self.serialize_tuple(len)
     }
 
     fn serialize_tuple_variant(
         self,
-        _name: &'static str,
+        name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        todo!()
+        Ok(SerializeVariant {
+            name,
+            variant,
+            inner: self.serialize_tuple(len)?,
+        })
     }
 
-    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+    // serde writes structs with flattened fields as maps of unknown length.
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
         Ok(SerializeMap {
+            serializer: self,
+            name: "",
+            flattened: len.is_none(),
             map: Map::new(),
             next_key: None,
         })
     }
 
-    fn serialize_struct(self, _name: &'static str, _lenn: usize) -> Result<Self::SerializeStruct> {
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
         Ok(SerializeMap {
+            serializer: self,
+            name,
+            flattened: false,
             map: Map::new(),
             next_key: None,
         })
@@ -234,21 +618,26 @@ impl serde::Serializer for Serializer {
 
     fn serialize_struct_variant(
         self,
-        _name: &'static str,
+        name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        todo!()
+        Ok(SerializeVariant {
+            name,
+            variant,
+            inner: self.serialize_struct(variant, len)?,
+        })
     }
 }
 
-pub struct SerializeVec {
+pub struct SerializeVec<'a> {
+    serializer: Serializer<'a>,
     vec: Vec<Value>,
     as_list: bool,
 }
 
-impl serde::ser::SerializeSeq for SerializeVec {
+impl serde::ser::SerializeSeq for SerializeVec<'_> {
     type Ok = Value;
     type Error = Error;
 
@@ -256,7 +645,7 @@ impl serde::ser::SerializeSeq for SerializeVec {
     where
         T: ?Sized + Serialize,
     {
-        self.vec.push(to_value(value)?);
+        self.vec.push(value.serialize(self.serializer)?);
         Ok(())
     }
 
@@ -269,7 +658,7 @@ impl serde::ser::SerializeSeq for SerializeVec {
     }
 }
 
-impl serde::ser::SerializeTuple for SerializeVec {
+impl serde::ser::SerializeTuple for SerializeVec<'_> {
     type Ok = Value;
     type Error = Error;
 
@@ -285,7 +674,7 @@ impl serde::ser::SerializeTuple for SerializeVec {
     }
 }
 
-impl serde::ser::SerializeTupleStruct for SerializeVec {
+impl serde::ser::SerializeTupleStruct for SerializeVec<'_> {
     type Ok = Value;
     type Error = Error;
 
@@ -301,12 +690,17 @@ impl serde::ser::SerializeTupleStruct for SerializeVec {
     }
 }
 
-pub struct SerializeMap {
+pub struct SerializeMap<'a> {
+    serializer: Serializer<'a>,
+    /// The name of the struct whose fields are serialized.
+    name: &'static str,
+    /// Whether this may be a struct with flattened fields.
+    flattened: bool,
     map: Map<Key, Value>,
     next_key: Option<Key>,
 }
 
-impl serde::ser::SerializeMap for SerializeMap {
+impl serde::ser::SerializeMap for SerializeMap<'_> {
     type Ok = Value;
     type Error = Error;
 
@@ -318,6 +712,25 @@ impl serde::ser::SerializeMap for SerializeMap {
         Ok(())
     }
 
+    // serde passes the names of the fields of a struct with flattened fields,
+    // its own and those of the flattened structs, as `&'static str`, where
+    // `collect_map` passes references to the keys. So only a bare `str` key
+    // is a field name.
+    fn serialize_entry<K, V>(&mut self, key: &K, value: &V) -> Result<()>
+    where
+        K: ?Sized + Serialize,
+        V: ?Sized + Serialize,
+    {
+        let key = match key.serialize(MapKeySerializer)? {
+            Key::String(s) if self.flattened && std::any::type_name::<K>() == "str" => {
+                self.serializer.options.fields.key(self.name, &s)
+            }
+            key => key,
+        };
+        self.map.insert(key, value.serialize(self.serializer)?);
+        Ok(())
+    }
+
     fn serialize_value<T>(&mut self, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
@@ -326,7 +739,7 @@ impl serde::ser::SerializeMap for SerializeMap {
         // Panic because this indicates a bug in the program rather than an
         // expected failure.
         let key = key.expect("serialize_value called before serialize_key");
-        self.map.insert(key, to_value(value)?);
+        self.map.insert(key, value.serialize(self.serializer)?);
         Ok(())
     }
 
@@ -335,10 +748,76 @@ impl serde::ser::SerializeMap for SerializeMap {
     }
 }
 
+impl serde::ser::SerializeStruct for SerializeMap<'_> {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = self.serializer.options.fields.key(self.name, key);
+        self.map.insert(key, value.serialize(self.serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        serde::ser::SerializeMap::end(self)
+    }
+}
+
+/// Collects the data of a tuple or struct variant, then wraps it as set by
+/// the [`EnumStyle`].
+pub struct SerializeVariant<S> {
+    name: &'static str,
+    variant: &'static str,
+    inner: S,
+}
+
+impl<'a> serde::ser::SerializeTupleVariant for SerializeVariant<SerializeVec<'a>> {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        serde::ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        let serializer = self.inner.serializer;
+        let data = serde::ser::SerializeSeq::end(self.inner)?;
+        serializer.variant(self.name, self.variant, data)
+    }
+}
+
+impl<'a> serde::ser::SerializeStructVariant for SerializeVariant<SerializeMap<'a>> {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        serde::ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        let serializer = self.inner.serializer;
+        let data = serde::ser::SerializeMap::end(self.inner)?;
+        serializer.variant(self.name, self.variant, data)
+    }
+}
+
 // Serializer for Key
 
 struct MapKeySerializer;
 
+fn invalid_key(kind: &str) -> Error {
+    Error::InvalidKey(kind.into())
+}
+
 impl serde::Serializer for MapKeySerializer {
     type Ok = Key;
     type Error = Error;
@@ -352,119 +831,132 @@ impl serde::Serializer for MapKeySerializer {
     type SerializeStructVariant = Impossible<Key, Error>;
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok> {
-        todo!()
+        Ok(Key::Boolean(v))
     }
 
     fn serialize_i8(self, v: i8) -> Result<Self::Ok> {
-        todo!()
+        self.serialize_i64(v as i64)
     }
 
     fn serialize_i16(self, v: i16) -> Result<Self::Ok> {
-        todo!()
+        self.serialize_i64(v as i64)
     }
 
     fn serialize_i32(self, v: i32) -> Result<Self::Ok> {
-        todo!()
+        self.serialize_i64(v as i64)
     }
 
     fn serialize_i64(self, v: i64) -> Result<Self::Ok> {
-        todo!()
+        Ok(Key::Integer(v))
     }
 
     fn serialize_u8(self, v: u8) -> Result<Self::Ok> {
-        todo!()
+        self.serialize_i64(v as i64)
     }
 
     fn serialize_u16(self, v: u16) -> Result<Self::Ok> {
-        todo!()
+        self.serialize_i64(v as i64)
     }
 
     fn serialize_u32(self, v: u32) -> Result<Self::Ok> {
-        todo!()
+        self.serialize_i64(v as i64)
     }
 
     fn serialize_u64(self, v: u64) -> Result<Self::Ok> {
-        todo!()
+        self.serialize_i128(v as i128)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok> {
+        i64::try_from(v)
+            .map(Key::Integer)
+            .map_err(|_| Error::InvalidKey(format!("{} is out of range", v)))
     }
 
-    fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
-        todo!()
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok> {
+        i64::try_from(v)
+            .map(Key::Integer)
+            .map_err(|_| Error::InvalidKey(format!("{} is out of range", v)))
     }
 
-    fn serialize_f64(self, v: f64) -> Result<Self::Ok> {
-        todo!()
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok> {
+        Err(invalid_key("a float"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok> {
+        Err(invalid_key("a float"))
     }
 
     fn serialize_char(self, v: char) -> Result<Self::Ok> {
-        todo!()
+        Ok(Key::Character(v))
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok> {
-        if v.starts_with(":") {
-            Ok(Key::Keyword(v.into()))
-        } else {
-            Ok(Key::Symbol(v.into()))
-        }
+        Ok(Key::String(v.into()))
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
-        todo!()
+        Ok(Key::String(str::from_utf8(v)?.into()))
     }
 
     fn serialize_none(self) -> Result<Self::Ok> {
-        todo!()
+        Err(invalid_key("nil"))
     }
 
     fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok>
     where
         T: Serialize,
     {
-        todo!()
+        value.serialize(self)
     }
 
     fn serialize_unit(self) -> Result<Self::Ok> {
-        todo!()
+        Err(invalid_key("nil"))
     }
 
-    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok> {
-        todo!()
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
+        Err(invalid_key("nil"))
     }
 
     fn serialize_unit_variant(
         self,
-        name: &'static str,
-        variant_index: u32,
+        _name: &'static str,
+        _variant_index: u32,
         variant: &'static str,
     ) -> Result<Self::Ok> {
-        todo!()
+        Keyword::parse(variant)
+            .map(Key::Keyword)
+            .map_err(|_| Error::InvalidKey(format!("`{}` isn't a valid keyword", variant)))
     }
 
     fn serialize_newtype_struct<T: ?Sized>(self, name: &'static str, value: &T) -> Result<Self::Ok>
     where
         T: Serialize,
     {
-        todo!()
+        match value.serialize(self)? {
+            Key::String(s) => Ok(marked_key(name, &s).unwrap_or(Key::String(s))),
+            key => Ok(key),
+        }
     }
 
     fn serialize_newtype_variant<T: ?Sized>(
         self,
-        name: &'static str,
-        variant_index: u32,
-        variant: &'static str,
-        value: &T,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
     ) -> Result<Self::Ok>
     where
         T: Serialize,
     {
-        todo!()
+        Err(invalid_key("an enum variant with data"))
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
-        todo!()
+        Err(invalid_key("a sequence"))
     }
 
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
-        todo!()
+        Err(invalid_key("a tuple"))
     }
 
     fn serialize_tuple_struct(
@@ -472,7 +964,7 @@ impl serde::Serializer for MapKeySerializer {
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        todo!()
+        Err(invalid_key("a tuple struct"))
     }
 
     fn serialize_tuple_variant(
@@ -482,15 +974,15 @@ impl serde::Serializer for MapKeySerializer {
         _variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        todo!()
+        Err(invalid_key("an enum variant with data"))
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        todo!()
+        Err(invalid_key("a map"))
     }
 
-    fn serialize_struct(self, _name: &'static str, _lenn: usize) -> Result<Self::SerializeStruct> {
-        todo!()
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(invalid_key("a struct"))
     }
 
     fn serialize_struct_variant(
@@ -500,22 +992,368 @@ impl serde::Serializer for MapKeySerializer {
         _variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        todo!()
+        Err(invalid_key("an enum variant with data"))
     }
 }
 
-impl serde::ser::SerializeStruct for SerializeMap {
-    type Ok = Value;
-    type Error = Error;
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
 
-    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
-    where
-        T: ?Sized + Serialize,
-    {
-        serde::ser::SerializeMap::serialize_entry(self, key, value)
+    use serde::Serialize;
+
+    use super::*;
+    use crate::{parser::parse_edn, to_value};
+
+    fn edn(s: &str) -> Value {
+        parse_edn(s).unwrap()
     }
 
-    fn end(self) -> Result<Value> {
-        serde::ser::SerializeMap::end(self)
+    #[test]
+    fn test_enums() {
+        #[derive(Serialize)]
+        enum E {
+            Unit,
+            Newtype(i64),
+            Tuple(i64, bool),
+            Struct { a: i64 },
+        }
+
+        let map = SerializerOptions::new();
+        let tagged = SerializerOptions::new().enum_style(EnumStyle::Tagged);
+        assert_eq!(map.to_value(E::Unit).unwrap(), edn(":Unit"));
+        assert_eq!(tagged.to_value(E::Unit).unwrap(), edn(":Unit"));
+        assert_eq!(map.to_value(E::Newtype(1)).unwrap(), edn("{:Newtype 1}"));
+        assert_eq!(tagged.to_value(E::Newtype(1)).unwrap(), edn("#E/Newtype 1"));
+        assert_eq!(
+            map.to_value(E::Tuple(1, true)).unwrap(),
+            edn("{:Tuple (1 true)}")
+        );
+        assert_eq!(
+            tagged.to_value(E::Tuple(1, true)).unwrap(),
+            edn("#E/Tuple (1 true)")
+        );
+        assert_eq!(
+            map.to_value(E::Struct { a: 1 }).unwrap(),
+            edn("{:Struct {:a 1}}")
+        );
+        assert_eq!(
+            tagged.to_value(E::Struct { a: 1 }).unwrap(),
+            edn("#E/Struct {:a 1}")
+        );
+        // Nested values use the same options.
+        assert_eq!(
+            tagged.to_value(vec![Some(E::Newtype(1))]).unwrap(),
+            edn("[#E/Newtype 1]")
+        );
+    }
+
+    #[test]
+    fn test_renamed_unit_variants() {
+        #[derive(Serialize)]
+        enum E {
+            #[serde(rename = "a-b")]
+            Valid,
+            #[serde(rename = "a b")]
+            Space,
+            #[serde(rename = "")]
+            Empty,
+        }
+
+        assert_eq!(to_value(E::Valid).unwrap(), edn(":a-b"));
+        assert!(to_value(E::Space).is_err());
+        assert!(to_value(E::Empty).is_err());
+    }
+
+    #[test]
+    fn test_renamed_variants() {
+        #[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+        enum E {
+            #[serde(rename = "a-b")]
+            Valid,
+            #[serde(rename = "a b")]
+            Space,
+            #[serde(rename = "1x")]
+            Digit(i64),
+            #[serde(rename = "a b")]
+            Tuple(i64, i64),
+        }
+
+        let map = SerializerOptions::new();
+        let tagged = SerializerOptions::new().enum_style(EnumStyle::Tagged);
+        assert_eq!(
+            map.to_value(BTreeMap::from([(E::Valid, 1)])).unwrap(),
+            edn("{:a-b 1}")
+        );
+        assert!(matches!(
+            map.to_value(BTreeMap::from([(E::Space, 1)])),
+            Err(Error::InvalidKey(_))
+        ));
+        // `:1x` is a keyword, but `E/1x` isn't a symbol.
+        assert_eq!(map.to_value(E::Digit(1)).unwrap(), edn("{:1x 1}"));
+        assert!(tagged.to_value(E::Digit(1)).is_err());
+        assert!(matches!(
+            map.to_value(E::Tuple(1, 2)),
+            Err(Error::InvalidKey(_))
+        ));
+        assert!(tagged.to_value(E::Tuple(1, 2)).is_err());
+    }
+
+    #[test]
+    fn test_enum_representations() {
+        #[derive(Serialize)]
+        #[serde(tag = "type")]
+        enum Internal {
+            Unit,
+            Struct { a: i64 },
+        }
+
+        #[derive(Serialize)]
+        #[serde(tag = "t", content = "c")]
+        enum Adjacent {
+            Unit,
+            Newtype(i64),
+        }
+
+        #[derive(Serialize)]
+        #[serde(untagged)]
+        enum Untagged {
+            Newtype(i64),
+            Struct { a: i64 },
+        }
+
+        assert_eq!(to_value(Internal::Unit).unwrap(), edn("{:type \"Unit\"}"));
+        assert_eq!(
+            to_value(Internal::Struct { a: 1 }).unwrap(),
+            edn("{:type \"Struct\" :a 1}")
+        );
+        assert_eq!(to_value(Adjacent::Unit).unwrap(), edn("{:t :Unit}"));
+        assert_eq!(
+            to_value(Adjacent::Newtype(1)).unwrap(),
+            edn("{:t :Newtype :c 1}")
+        );
+        assert_eq!(to_value(Untagged::Newtype(1)).unwrap(), edn("1"));
+        assert_eq!(to_value(Untagged::Struct { a: 1 }).unwrap(), edn("{:a 1}"));
+    }
+
+    #[test]
+    fn test_field_keys() {
+        #[derive(Serialize)]
+        struct UserAccount {
+            user_name: &'static str,
+            #[serde(rename = "hello world")]
+            greeting: bool,
+        }
+
+        #[derive(Serialize)]
+        enum E {
+            Struct { a_b: i64 },
+        }
+
+        let user = UserAccount {
+            user_name: "x",
+            greeting: true,
+        };
+        let cases = [
+            (
+                FieldKeys::Keyword,
+                false,
+                "{:user_name \"x\" \"hello world\" true}",
+            ),
+            (
+                FieldKeys::Keyword,
+                true,
+                "{:user-name \"x\" \"hello world\" true}",
+            ),
+            (
+                FieldKeys::NamespacedKeyword,
+                true,
+                "{:user-account/user-name \"x\" \"hello world\" true}",
+            ),
+            (
+                FieldKeys::String,
+                false,
+                "{\"user_name\" \"x\" \"hello world\" true}",
+            ),
+            (
+                FieldKeys::Symbol,
+                true,
+                "{user-name \"x\" \"hello world\" true}",
+            ),
+        ];
+        for (keys, kebab, expected) in cases {
+            let options = SerializerOptions::new().field_keys(keys).kebab_case(kebab);
+            assert_eq!(
+                options.to_value(&user).unwrap(),
+                edn(expected),
+                "{:?}",
+                keys
+            );
+        }
+        let options = SerializerOptions::new().field_keys(FieldKeys::NamespacedKeyword);
+        assert_eq!(
+            options.to_value(E::Struct { a_b: 1 }).unwrap(),
+            edn("{:Struct {:struct/a_b 1}}")
+        );
+        // Names outside ASCII aren't read as keywords or symbols.
+        #[derive(Serialize)]
+        struct Menu {
+            café: bool,
+        }
+        assert_eq!(
+            to_value(Menu { café: true }).unwrap(),
+            edn("{\"café\" true}")
+        );
+
+        // Map keys aren't field names.
+        assert_eq!(
+            options.to_value(BTreeMap::from([("a", 1)])).unwrap(),
+            edn("{\"a\" 1}")
+        );
+    }
+
+    #[test]
+    fn test_flatten() {
+        #[derive(Serialize)]
+        struct Inner {
+            b_c: i64,
+        }
+
+        #[derive(Serialize)]
+        struct Outer {
+            a: i64,
+            #[serde(flatten)]
+            inner: Inner,
+        }
+
+        let outer = Outer {
+            a: 1,
+            inner: Inner { b_c: 2 },
+        };
+        let cases = [
+            (FieldKeys::Keyword, "{:a 1 :b-c 2}"),
+            (FieldKeys::NamespacedKeyword, "{:a 1 :b-c 2}"),
+            (FieldKeys::String, "{\"a\" 1 \"b-c\" 2}"),
+            (FieldKeys::Symbol, "{a 1 b-c 2}"),
+        ];
+        for (keys, expected) in cases {
+            let options = SerializerOptions::new().field_keys(keys).kebab_case(true);
+            assert_eq!(
+                options.to_value(&outer).unwrap(),
+                edn(expected),
+                "{:?}",
+                keys
+            );
+        }
+
+        // Maps of unknown length keep their string keys.
+        struct Filtered<K>(BTreeMap<K, i64>);
+
+        impl<K: Serialize> Serialize for Filtered<K> {
+            fn serialize<S: serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                serializer.collect_map(self.0.iter().filter(|(_, v)| **v > 0))
+            }
+        }
+
+        let options = SerializerOptions::new().kebab_case(true);
+        let map = Filtered(BTreeMap::from([("a_b".to_string(), 1), ("c".into(), 0)]));
+        assert_eq!(options.to_value(&map).unwrap(), edn("{\"a_b\" 1}"));
+        let map = Filtered(BTreeMap::from([("a_b", 1), ("c", 0)]));
+        assert_eq!(options.to_value(&map).unwrap(), edn("{\"a_b\" 1}"));
+    }
+
+    #[test]
+    fn test_serialize_json() {
+        let v = parse_edn(
+            r#"{:inst #inst "2020-01-01T00:00:00.000+01:00"
+                :uuid #uuid "f81d4fae-7dec-11d0-a765-00a0c91e6bf6"
+                :tagged #point [1 2.5]
+                :nil nil}"#,
+        )
+        .unwrap();
+        let json: serde_json::Value = serde_json::to_value(&v).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                ":inst": "2020-01-01T00:00:00+01:00",
+                ":uuid": "f81d4fae-7dec-11d0-a765-00a0c91e6bf6",
+                ":tagged": {"#point": [1, 2.5]},
+                ":nil": null,
+            })
+        );
+        let keys = parse_edn("{1 :a, true :b, #uuid \"f81d4fae-7dec-11d0-a765-00a0c91e6bf6\" :c}")
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&keys).unwrap(),
+            serde_json::json!({
+                "1": ":a",
+                "true": ":b",
+                "f81d4fae-7dec-11d0-a765-00a0c91e6bf6": ":c",
+            })
+        );
+    }
+
+    #[test]
+    fn test_map_keys() {
+        let map: BTreeMap<&str, i64> = [(":a", 1), ("b", 2), ("hello world", 3), (":", 4), ("", 5)]
+            .into_iter()
+            .collect();
+        let Value::Map(m) = to_value(map).unwrap() else {
+            panic!("not a map")
+        };
+        // String keys stay strings, whatever they hold.
+        assert_eq!(m.get(&Key::String(":a".into())), Some(&Value::Integer(1)));
+        assert_eq!(m.get(&Key::String("b".into())), Some(&Value::Integer(2)));
+        assert_eq!(
+            m.get(&Key::String("hello world".into())),
+            Some(&Value::Integer(3))
+        );
+        assert_eq!(m.get(&Key::String(":".into())), Some(&Value::Integer(4)));
+        assert_eq!(m.get(&Key::String("".into())), Some(&Value::Integer(5)));
+
+        #[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+        enum Color {
+            Red,
+            Blue(u8),
+        }
+
+        let uuid = "f81d4fae-7dec-11d0-a765-00a0c91e6bf6";
+        let keys = (
+            BTreeMap::from([(1u64, 1), (2, 2)]),
+            BTreeMap::from([('a', 1), ('b', 2)]),
+            BTreeMap::from([(true, 1)]),
+            BTreeMap::from([(Color::Red, 1)]),
+            BTreeMap::from([(uuid, 1)]),
+        );
+        assert_eq!(
+            to_value(keys).unwrap(),
+            edn(&format!(
+                "({{1 1 2 2}} {{\\a 1 \\b 2}} {{true 1}} {{:Red 1}} {{\"{}\" 1}})",
+                uuid
+            ))
+        );
+
+        // EDN keys and values keep their type.
+        let v = edn(&format!(
+            "{{a 1, :b/c [:d e], #uuid \"{0}\" #uuid \"{0}\", \"f\" \"g\"}}",
+            uuid
+        ));
+        assert_eq!(to_value(&v).unwrap(), v);
+
+        assert!(matches!(
+            to_value(BTreeMap::from([(u64::MAX, 1)])),
+            Err(Error::InvalidKey(_))
+        ));
+        assert!(matches!(
+            to_value(BTreeMap::from([(Color::Blue(1), 1)])),
+            Err(Error::InvalidKey(_))
+        ));
+        assert!(matches!(
+            to_value(BTreeMap::from([(vec![1], 1)])),
+            Err(Error::InvalidKey(_))
+        ));
     }
 }
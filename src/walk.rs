@@ -0,0 +1,625 @@
+//! Generic traversal of `Value` trees.
+//!
+//! This module provides `clojure.walk` style [`Value::prewalk`] and
+//! [`Value::postwalk`] transforms, the [`Visitor`] and [`VisitorMut`] traits,
+//! and the [`Nodes`] iterator over every `(path, &Value)` in a tree.
+//!
+//! All traversals keep their own explicit stack, so arbitrarily deep trees
+//! don't overflow the call stack.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use chrono::{DateTime, FixedOffset};
+use uuid::Uuid;
+
+use crate::symbol::{Keyword, Symbol};
+use crate::value::Key;
+use crate::{Map, Value};
+
+/// One step from a collection to one of its children.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// The element at a position in a vector or list.
+    Index(usize),
+    /// The value stored under a key in a map.
+    Key(Key),
+    /// An element of a set. Set elements have no stable address.
+    Member,
+    /// The form wrapped by a tagged literal.
+    Tagged,
+}
+
+/// The location of a node inside a `Value`, from the root down.
+pub type Path = Vec<PathSegment>;
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathSegment::Index(i) => write!(f, "{}", i),
+            PathSegment::Key(k) => write!(f, "{}", k),
+            PathSegment::Member => f.write_str("#{}"),
+            PathSegment::Tagged => f.write_str("#"),
+        }
+    }
+}
+
+/// Visits every node of a `Value` by shared reference.
+///
+/// [`visit_value`](Visitor::visit_value) is called for every node, followed
+/// by the callback for the node's variant. Collections are visited before
+/// their children. Map keys are passed to [`visit_key`](Visitor::visit_key)
+/// with the path of the map holding them.
+#[allow(unused_variables)]
+pub trait Visitor {
+    fn visit_value(&mut self, path: &[PathSegment], value: &Value) {}
+    fn visit_nil(&mut self, path: &[PathSegment]) {}
+    fn visit_boolean(&mut self, path: &[PathSegment], b: bool) {}
+    fn visit_integer(&mut self, path: &[PathSegment], i: i64) {}
+    fn visit_bigint(&mut self, path: &[PathSegment], i: i128) {}
+    fn visit_float(&mut self, path: &[PathSegment], f: f64) {}
+    fn visit_string(&mut self, path: &[PathSegment], s: &str) {}
+    fn visit_symbol(&mut self, path: &[PathSegment], s: &Symbol) {}
+    fn visit_keyword(&mut self, path: &[PathSegment], k: &Keyword) {}
+    fn visit_vector(&mut self, path: &[PathSegment], v: &[Value]) {}
+    fn visit_list(&mut self, path: &[PathSegment], l: &[Value]) {}
+    fn visit_set(&mut self, path: &[PathSegment], s: &HashSet<Value>) {}
+    fn visit_map(&mut self, path: &[PathSegment], m: &Map<Key, Value>) {}
+    fn visit_key(&mut self, path: &[PathSegment], k: &Key) {}
+    fn visit_instant(&mut self, path: &[PathSegment], i: &DateTime<FixedOffset>) {}
+    fn visit_uuid(&mut self, path: &[PathSegment], u: &Uuid) {}
+    fn visit_character(&mut self, path: &[PathSegment], c: char) {}
+    fn visit_tagged(&mut self, path: &[PathSegment], tag: &Symbol, value: &Value) {}
+}
+
+/// Visits every node of a `Value` by mutable reference.
+///
+/// Callbacks are made in the same order as for [`Visitor`]. Changes made to a
+/// collection, including replacing a node through
+/// [`visit_value_mut`](VisitorMut::visit_value_mut), are seen when its
+/// children are visited.
+#[allow(unused_variables)]
+pub trait VisitorMut {
+    fn visit_value_mut(&mut self, path: &[PathSegment], value: &mut Value) {}
+    fn visit_nil_mut(&mut self, path: &[PathSegment]) {}
+    fn visit_boolean_mut(&mut self, path: &[PathSegment], b: &mut bool) {}
+    fn visit_integer_mut(&mut self, path: &[PathSegment], i: &mut i64) {}
+    fn visit_bigint_mut(&mut self, path: &[PathSegment], i: &mut i128) {}
+    fn visit_float_mut(&mut self, path: &[PathSegment], f: &mut f64) {}
+    fn visit_string_mut(&mut self, path: &[PathSegment], s: &mut String) {}
+    fn visit_symbol_mut(&mut self, path: &[PathSegment], s: &mut Symbol) {}
+    fn visit_keyword_mut(&mut self, path: &[PathSegment], k: &mut Keyword) {}
+    fn visit_vector_mut(&mut self, path: &[PathSegment], v: &mut Vec<Value>) {}
+    fn visit_list_mut(&mut self, path: &[PathSegment], l: &mut Vec<Value>) {}
+    fn visit_set_mut(&mut self, path: &[PathSegment], s: &mut HashSet<Value>) {}
+    fn visit_map_mut(&mut self, path: &[PathSegment], m: &mut Map<Key, Value>) {}
+    fn visit_key_mut(&mut self, path: &[PathSegment], k: &mut Key) {}
+    fn visit_instant_mut(&mut self, path: &[PathSegment], i: &mut DateTime<FixedOffset>) {}
+    fn visit_uuid_mut(&mut self, path: &[PathSegment], u: &mut Uuid) {}
+    fn visit_character_mut(&mut self, path: &[PathSegment], c: &mut char) {}
+    fn visit_tagged_mut(&mut self, path: &[PathSegment], tag: &mut Symbol, value: &mut Value) {}
+}
+
+impl Value {
+    /// Walks the tree top-down, replacing every node with the result of `f`.
+    ///
+    /// Like `clojure.walk/prewalk`, `f` sees each node before its children,
+    /// and the children of the node it returns are walked next. Map keys are
+    /// passed to `f` as values; a key that `f` turns into a value that can't
+    /// be a key, such as a vector, is kept as it was.
+    pub fn prewalk<F>(self, f: F) -> Value
+    where
+        F: FnMut(Value) -> Value,
+    {
+        walk_owned(self, &mut PreWalk(f))
+    }
+
+    /// Walks the tree bottom-up, replacing every node with the result of `f`.
+    ///
+    /// Like `clojure.walk/postwalk`, `f` sees each node after its children
+    /// have been replaced. Map keys are passed to `f` as values; a key that
+    /// `f` turns into a value that can't be a key is kept as it was.
+    pub fn postwalk<F>(self, f: F) -> Value
+    where
+        F: FnMut(Value) -> Value,
+    {
+        walk_owned(self, &mut PostWalk(f))
+    }
+
+    /// Calls the visitor for every node in the tree, parents first.
+    pub fn visit<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        let mut nodes = self.nodes();
+        while let Some((path, value)) = nodes.next_ref() {
+            visitor.visit_value(path, value);
+            match value {
+                Value::Nil => visitor.visit_nil(path),
+                Value::Boolean(b) => visitor.visit_boolean(path, *b),
+                Value::Integer(i) => visitor.visit_integer(path, *i),
+                Value::BigInt(i) => visitor.visit_bigint(path, *i),
+                Value::Float(f) => visitor.visit_float(path, f.0),
+                Value::String(s) => visitor.visit_string(path, s),
+                Value::Symbol(s) => visitor.visit_symbol(path, s),
+                Value::Keyword(k) => visitor.visit_keyword(path, k),
+                Value::Vector(v) => visitor.visit_vector(path, v),
+                Value::List(l) => visitor.visit_list(path, l),
+                Value::Set(s) => visitor.visit_set(path, s),
+                Value::Map(m) => {
+                    visitor.visit_map(path, m);
+                    for k in m.keys() {
+                        visitor.visit_key(path, k);
+                    }
+                }
+                Value::Instant(i) => visitor.visit_instant(path, i),
+                Value::Uuid(u) => visitor.visit_uuid(path, u),
+                Value::Character(c) => visitor.visit_character(path, *c),
+                Value::Tagged(t, v) => visitor.visit_tagged(path, t, v),
+            }
+        }
+    }
+
+    /// Calls the visitor for every node in the tree, parents first, allowing
+    /// it to modify nodes in place.
+    pub fn visit_mut<V: VisitorMut + ?Sized>(&mut self, visitor: &mut V) {
+        let value = std::mem::replace(self, Value::Nil);
+        *self = walk_owned(value, &mut WalkMut(visitor));
+    }
+
+    /// Returns an iterator over every node in the tree together with its
+    /// path, parents first.
+    pub fn nodes(&self) -> Nodes<'_> {
+        Nodes {
+            stack: vec![(0, None, self)],
+            path: Vec::new(),
+        }
+    }
+}
+
+/// A depth-first iterator over the nodes of a `Value`.
+///
+/// This struct is created by [`Value::nodes`].
+pub struct Nodes<'a> {
+    /// Nodes still to visit, with their depth and the segment leading to them.
+    stack: Vec<(usize, Option<PathSegment>, &'a Value)>,
+    path: Path,
+}
+
+impl<'a> Nodes<'a> {
+    /// Advances the iterator without copying the path.
+    fn next_ref(&mut self) -> Option<(&[PathSegment], &'a Value)> {
+        let (depth, segment, value) = self.stack.pop()?;
+        self.path.truncate(depth.saturating_sub(1));
+        self.path.extend(segment);
+
+        let start = self.stack.len();
+        let depth = depth + 1;
+        match value {
+            Value::Vector(v) | Value::List(v) => self.stack.extend(
+                v.iter()
+                    .enumerate()
+                    .map(|(i, v)| (depth, Some(PathSegment::Index(i)), v)),
+            ),
+            Value::Set(s) => self
+                .stack
+                .extend(s.iter().map(|v| (depth, Some(PathSegment::Member), v))),
+            Value::Map(m) => self.stack.extend(
+                m.iter()
+                    .map(|(k, v)| (depth, Some(PathSegment::Key(k.clone())), v)),
+            ),
+            Value::Tagged(_, v) => self.stack.push((depth, Some(PathSegment::Tagged), v)),
+            _ => {}
+        }
+        // Children were pushed in order; reverse them so the first is
+        // popped first.
+        self.stack[start..].reverse();
+        Some((&self.path, value))
+    }
+}
+
+impl<'a> Iterator for Nodes<'a> {
+    type Item = (Path, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_ref().map(|(path, value)| (path.to_vec(), value))
+    }
+}
+
+/// The callbacks driving `walk_owned`.
+trait Walk {
+    /// Whether the paths handed to the callbacks need to be maintained.
+    const TRACK_PATH: bool;
+
+    /// Called on a node before its children are walked.
+    fn pre(&mut self, path: &[PathSegment], value: Value) -> Value;
+
+    /// Called on a node after its children have been walked.
+    fn post(&mut self, path: &[PathSegment], value: Value) -> Value;
+
+    /// Called on each map key, with the path of the map.
+    fn key(&mut self, path: &[PathSegment], key: Key) -> Key;
+}
+
+struct PreWalk<F>(F);
+
+impl<F: FnMut(Value) -> Value> Walk for PreWalk<F> {
+    const TRACK_PATH: bool = false;
+
+    fn pre(&mut self, _path: &[PathSegment], value: Value) -> Value {
+        (self.0)(value)
+    }
+
+    fn post(&mut self, _path: &[PathSegment], value: Value) -> Value {
+        value
+    }
+
+    fn key(&mut self, _path: &[PathSegment], key: Key) -> Key {
+        walked_key(&mut self.0, key)
+    }
+}
+
+struct PostWalk<F>(F);
+
+impl<F: FnMut(Value) -> Value> Walk for PostWalk<F> {
+    const TRACK_PATH: bool = false;
+
+    fn pre(&mut self, _path: &[PathSegment], value: Value) -> Value {
+        value
+    }
+
+    fn post(&mut self, _path: &[PathSegment], value: Value) -> Value {
+        (self.0)(value)
+    }
+
+    fn key(&mut self, _path: &[PathSegment], key: Key) -> Key {
+        walked_key(&mut self.0, key)
+    }
+}
+
+/// Passes a map key to `f`, keeping the key if `f` returns a value that
+/// can't be one.
+fn walked_key<F: FnMut(Value) -> Value>(f: &mut F, key: Key) -> Key {
+    f(key.clone().into()).try_into().unwrap_or(key)
+}
+
+struct WalkMut<'a, V: ?Sized>(&'a mut V);
+
+impl<'a, V: VisitorMut + ?Sized> Walk for WalkMut<'a, V> {
+    const TRACK_PATH: bool = true;
+
+    fn pre(&mut self, path: &[PathSegment], mut value: Value) -> Value {
+        let visitor = &mut *self.0;
+        visitor.visit_value_mut(path, &mut value);
+        match &mut value {
+            Value::Nil => visitor.visit_nil_mut(path),
+            Value::Boolean(b) => visitor.visit_boolean_mut(path, b),
+            Value::Integer(i) => visitor.visit_integer_mut(path, i),
+            Value::BigInt(i) => visitor.visit_bigint_mut(path, i),
+            Value::Float(f) => visitor.visit_float_mut(path, &mut f.0),
+            Value::String(s) => visitor.visit_string_mut(path, s),
+            Value::Symbol(s) => visitor.visit_symbol_mut(path, s),
+            Value::Keyword(k) => visitor.visit_keyword_mut(path, k),
+            Value::Vector(v) => visitor.visit_vector_mut(path, v),
+            Value::List(l) => visitor.visit_list_mut(path, l),
+            Value::Set(s) => visitor.visit_set_mut(path, s),
+            Value::Map(m) => visitor.visit_map_mut(path, m),
+            Value::Instant(i) => visitor.visit_instant_mut(path, i),
+            Value::Uuid(u) => visitor.visit_uuid_mut(path, u),
+            Value::Character(c) => visitor.visit_character_mut(path, c),
+            Value::Tagged(t, v) => visitor.visit_tagged_mut(path, t, v),
+        }
+        value
+    }
+
+    fn post(&mut self, _path: &[PathSegment], value: Value) -> Value {
+        value
+    }
+
+    fn key(&mut self, path: &[PathSegment], mut key: Key) -> Key {
+        self.0.visit_key_mut(path, &mut key);
+        key
+    }
+}
+
+/// A collection whose children are being walked.
+enum Frame {
+    Seq {
+        items: std::vec::IntoIter<Value>,
+        done: Vec<Value>,
+        list: bool,
+    },
+    Set {
+        items: std::collections::hash_set::IntoIter<Value>,
+        done: Vec<Value>,
+    },
+    Map {
+        items: <Map<Key, Value> as IntoIterator>::IntoIter,
+        done: Vec<(Key, Value)>,
+        key: Option<Key>,
+    },
+    Tagged {
+        tag: Symbol,
+        value: Option<Value>,
+        done: Option<Value>,
+    },
+}
+
+impl Frame {
+    /// Takes the next child to walk, along with the segment leading to it.
+    fn next_child<W: Walk>(
+        &mut self,
+        walk: &mut W,
+        path: &[PathSegment],
+    ) -> Option<(PathSegment, Value)> {
+        match self {
+            Frame::Seq { items, done, .. } => {
+                items.next().map(|v| (PathSegment::Index(done.len()), v))
+            }
+            Frame::Set { items, .. } => items.next().map(|v| (PathSegment::Member, v)),
+            Frame::Map { items, key, .. } => items.next().map(|(k, v)| {
+                let k = walk.key(path, k);
+                let segment = if W::TRACK_PATH {
+                    PathSegment::Key(k.clone())
+                } else {
+                    PathSegment::Member
+                };
+                *key = Some(k);
+                (segment, v)
+            }),
+            Frame::Tagged { value, .. } => value.take().map(|v| (PathSegment::Tagged, v)),
+        }
+    }
+
+    /// Stores a walked child.
+    fn push(&mut self, child: Value) {
+        match self {
+            Frame::Seq { done, .. } | Frame::Set { done, .. } => done.push(child),
+            Frame::Map { done, key, .. } => {
+                done.push((key.take().expect("map value walked before its key"), child))
+            }
+            Frame::Tagged { done, .. } => *done = Some(child),
+        }
+    }
+
+    /// Rebuilds the collection from its walked children.
+    fn finish(self) -> Value {
+        match self {
+            Frame::Seq {
+                done, list: false, ..
+            } => Value::Vector(done.into_iter().collect()),
+            Frame::Seq {
+                done, list: true, ..
+            } => Value::List(done.into_iter().collect()),
+            Frame::Set { done, .. } => Value::Set(done.into_iter().collect()),
+            Frame::Map { done, .. } => Value::Map(done.into_iter().collect()),
+            Frame::Tagged { tag, done, .. } => {
+                Value::Tagged(tag, Box::new(done.expect("tagged value not walked")))
+            }
+        }
+    }
+}
+
+fn walk_owned<W: Walk>(value: Value, walk: &mut W) -> Value {
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut path: Path = Vec::new();
+    let mut next = walk.pre(&path, value);
+
+    loop {
+        // Open the next node: collections become frames, leaves are done.
+        let mut done = match next {
+            Value::Vector(v) => {
+                stack.push(Frame::Seq {
+                    done: Vec::with_capacity(v.len()),
+                    items: v.into_iter(),
+                    list: false,
+                });
+                None
+            }
+            Value::List(v) => {
+                stack.push(Frame::Seq {
+                    done: Vec::with_capacity(v.len()),
+                    items: v.into_iter(),
+                    list: true,
+                });
+                None
+            }
+            Value::Set(s) => {
+                stack.push(Frame::Set {
+                    done: Vec::with_capacity(s.len()),
+                    items: s.into_iter(),
+                });
+                None
+            }
+            Value::Map(m) => {
+                stack.push(Frame::Map {
+                    done: Vec::with_capacity(m.len()),
+                    items: m.into_iter(),
+                    key: None,
+                });
+                None
+            }
+            Value::Tagged(tag, v) => {
+                stack.push(Frame::Tagged {
+                    tag,
+                    value: Some(*v),
+                    done: None,
+                });
+                None
+            }
+            leaf => Some(walk.post(&path, leaf)),
+        };
+
+        // Hand finished nodes to their parents until a parent has another
+        // child to walk.
+        loop {
+            let frame = match stack.last_mut() {
+                Some(frame) => frame,
+                None => return done.expect("walk finished without a value"),
+            };
+            if let Some(child) = done.take() {
+                frame.push(child);
+                if W::TRACK_PATH {
+                    path.pop();
+                }
+            }
+            match frame.next_child(walk, &path) {
+                Some((segment, child)) => {
+                    if W::TRACK_PATH {
+                        path.push(segment);
+                    }
+                    next = walk.pre(&path, child);
+                    break;
+                }
+                None => {
+                    let frame = stack.pop().expect("frame disappeared");
+                    done = Some(walk.post(&path, frame.finish()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_edn;
+
+    #[test]
+    fn test_prewalk_postwalk() {
+        let value = parse_edn("{:a/x [1 (2 3)] :b #{4} :c #foo 5}").unwrap();
+
+        let inc = |v| match v {
+            Value::Integer(i) => Value::Integer(i + 1),
+            Value::Keyword(k) => Value::Keyword(Keyword::new(None, k.name())),
+            v => v,
+        };
+        let expected = parse_edn("{:x [2 (3 4)] :b #{5} :c #foo 6}").unwrap();
+        assert_eq!(value.clone().prewalk(inc), expected);
+        assert_eq!(value.clone().postwalk(inc), expected);
+
+        // prewalk descends into what `f` returns, postwalk doesn't.
+        let expand = |v| match v {
+            Value::Integer(1) => parse_edn("[1 1]").unwrap(),
+            v => v,
+        };
+        let value = parse_edn("[1]").unwrap();
+        assert_eq!(value.clone().postwalk(expand).to_string(), "[[1 1]]");
+        let mut seen = Vec::new();
+        value.prewalk(|v| {
+            seen.push(v.to_string());
+            v
+        });
+        assert_eq!(seen, ["[1]", "1"]);
+
+        // Keys can't become vectors, so they stay as they were.
+        let value = parse_edn("{:a 1 2 #{3}}").unwrap();
+        let wrap = |v| match v {
+            Value::Keyword(_) | Value::Integer(_) => Value::Vector(vec![v.to_string().into()]),
+            v => v,
+        };
+        let expected = parse_edn(r#"{:a ["1"] 2 #{["3"]}}"#).unwrap();
+        assert_eq!(value.clone().postwalk(wrap), expected);
+        assert_eq!(value.prewalk(wrap), expected);
+    }
+
+    #[test]
+    fn test_nodes() {
+        let value = parse_edn("{:a [1 {:b 2}]}").unwrap();
+        let paths: Vec<String> = value
+            .nodes()
+            .map(|(path, v)| {
+                let path: Vec<String> = path.iter().map(ToString::to_string).collect();
+                format!("[{}] {}", path.join(" "), v)
+            })
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "[] {:a [1 {:b 2}]}",
+                "[:a] [1 {:b 2}]",
+                "[:a 0] 1",
+                "[:a 1] {:b 2}",
+                "[:a 1 :b] 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_visitors() {
+        #[derive(Default)]
+        struct Refs(Vec<(Path, i64)>);
+
+        impl Visitor for Refs {
+            fn visit_map(&mut self, path: &[PathSegment], m: &Map<Key, Value>) {
+                if let Some(Value::Integer(id)) = m.get(&Key::from(":db/id")) {
+                    self.0.push((path.to_vec(), *id));
+                }
+            }
+        }
+
+        let mut value = parse_edn("[{:db/id 1 :child {:db/id 2}} {:name \"x\"}]").unwrap();
+        let mut refs = Refs::default();
+        value.visit(&mut refs);
+        assert_eq!(
+            refs.0,
+            [
+                (vec![PathSegment::Index(0)], 1),
+                (
+                    vec![PathSegment::Index(0), PathSegment::Key(":child".into())],
+                    2
+                ),
+            ]
+        );
+
+        struct Rename;
+
+        impl VisitorMut for Rename {
+            fn visit_key_mut(&mut self, _path: &[PathSegment], k: &mut Key) {
+                if let Key::Keyword(s) = k {
+                    *k = Key::Keyword(Keyword::new(None, s.name()));
+                }
+            }
+
+            fn visit_string_mut(&mut self, path: &[PathSegment], s: &mut String) {
+                s.push_str(&path.len().to_string());
+            }
+        }
+
+        value.visit_mut(&mut Rename);
+        assert_eq!(
+            value,
+            parse_edn("[{:id 1 :child {:id 2}} {:name \"x2\"}]").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_deep_tree() {
+        #[derive(Default)]
+        struct Deepest(usize, Option<i64>);
+
+        impl Visitor for Deepest {
+            fn visit_integer(&mut self, path: &[PathSegment], i: i64) {
+                *self = Deepest(path.len(), Some(i));
+            }
+        }
+
+        let depth = 1_000_000;
+        let mut value = Value::Integer(0);
+        for _ in 0..depth {
+            value = Value::Vector(std::iter::once(value).collect());
+        }
+
+        let mut value = value.postwalk(|v| match v {
+            Value::Integer(i) => Value::Integer(i + 1),
+            v => v,
+        });
+        value = value.prewalk(|v| v);
+        let mut deepest = Deepest::default();
+        value.visit(&mut deepest);
+        assert_eq!(deepest.0, depth);
+        assert_eq!(deepest.1, Some(1));
+
+        // Dropping is recursive, so take the tree apart by hand.
+        while let Value::Vector(v) = value {
+            value = v.into_iter().next().unwrap();
+        }
+    }
+}
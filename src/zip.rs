@@ -0,0 +1,335 @@
+//! A `clojure.zip` style zipper for navigating and editing `Value` trees.
+//!
+//! Vectors, lists, sets, maps and tagged literals are branches. The children
+//! of a map are its entries, each shown as a `[key value]` vector that can in
+//! turn be entered to reach the key and the value.
+//!
+//! ```
+//! use edn::{parser::parse_edn, zip::Zipper};
+//!
+//! let mut z = Zipper::new(parse_edn("[1 [2 3]]").unwrap());
+//! z.down().unwrap().right().unwrap().down().unwrap();
+//! z.replace(edn::Value::Integer(20)).unwrap();
+//! assert_eq!(z.root().to_string(), "[1 [20 3]]");
+//! ```
+
+use crate::error::{Error, Result};
+use crate::symbol::Symbol;
+use crate::value::Key;
+use crate::Value;
+
+/// A location in a `Value` tree, owning the whole tree.
+#[derive(Clone, Debug)]
+pub struct Zipper {
+    focus: Value,
+    path: Vec<Crumb>,
+}
+
+/// What is left of a branch after descending into one of its children.
+#[derive(Clone, Debug)]
+struct Crumb {
+    branch: Branch,
+    left: Vec<Value>,
+    /// Siblings to the right of the focus, nearest last.
+    right: Vec<Value>,
+}
+
+#[derive(Clone, Debug)]
+enum Branch {
+    Vector,
+    List,
+    Set,
+    Map,
+    Entry,
+    Tagged(Symbol),
+}
+
+impl Zipper {
+    /// Creates a zipper focused on the root of `value`.
+    pub fn new(value: Value) -> Self {
+        Zipper {
+            focus: value,
+            path: Vec::new(),
+        }
+    }
+
+    /// Returns the value at the current location.
+    pub fn node(&self) -> &Value {
+        &self.focus
+    }
+
+    /// Returns true if the current location is the root.
+    pub fn is_root(&self) -> bool {
+        self.path.is_empty()
+    }
+
+    /// Moves to the leftmost child of the current node.
+    ///
+    /// Returns `None`, leaving the zipper where it was, if the node is not a
+    /// branch or has no children.
+    pub fn down(&mut self) -> Option<&mut Self> {
+        let in_map = matches!(self.parent(), Some(Branch::Map));
+        let branch = match &self.focus {
+            Value::Vector(v) if in_map && !v.is_empty() => Branch::Entry,
+            Value::Vector(v) if !v.is_empty() => Branch::Vector,
+            Value::List(v) if !v.is_empty() => Branch::List,
+            Value::Set(s) if !s.is_empty() => Branch::Set,
+            Value::Map(m) if !m.is_empty() => Branch::Map,
+            Value::Tagged(t, _) => Branch::Tagged(t.clone()),
+            _ => return None,
+        };
+
+        let mut children: Vec<Value> = match std::mem::replace(&mut self.focus, Value::Nil) {
+            Value::Vector(v) | Value::List(v) => v.into_iter().collect(),
+            Value::Set(s) => s.into_iter().collect(),
+            Value::Map(m) => m.into_iter().map(|(k, v)| entry(k, v)).collect(),
+            Value::Tagged(_, v) => vec![*v],
+            _ => unreachable!(),
+        };
+        children.reverse();
+        self.focus = children.pop().expect("branch has children");
+        self.path.push(Crumb {
+            branch,
+            left: Vec::new(),
+            right: children,
+        });
+        Some(self)
+    }
+
+    /// Moves to the parent of the current node, rebuilding it.
+    ///
+    /// Returns `None` at the root.
+    pub fn up(&mut self) -> Option<&mut Self> {
+        let Crumb {
+            branch,
+            mut left,
+            right,
+        } = self.path.pop()?;
+        let focus = std::mem::replace(&mut self.focus, Value::Nil);
+        left.push(focus);
+        left.extend(right.into_iter().rev());
+        self.focus = build(branch, left);
+        Some(self)
+    }
+
+    /// Moves to the right sibling of the current node.
+    ///
+    /// Returns `None` if this is the rightmost node or the root.
+    pub fn right(&mut self) -> Option<&mut Self> {
+        let crumb = self.path.last_mut()?;
+        let next = crumb.right.pop()?;
+        crumb.left.push(std::mem::replace(&mut self.focus, next));
+        Some(self)
+    }
+
+    /// Moves to the left sibling of the current node.
+    ///
+    /// Returns `None` if this is the leftmost node or the root.
+    pub fn left(&mut self) -> Option<&mut Self> {
+        let crumb = self.path.last_mut()?;
+        let prev = crumb.left.pop()?;
+        crumb.right.push(std::mem::replace(&mut self.focus, prev));
+        Some(self)
+    }
+
+    /// Replaces the current node, keeping the location.
+    ///
+    /// Fails if the replacement can't stand in this position: map entries
+    /// must stay `[key value]` vectors and entry keys must stay valid keys.
+    pub fn replace(&mut self, value: Value) -> Result<&mut Self> {
+        match self.parent() {
+            Some(Branch::Map) => check_entry(&value)?,
+            Some(Branch::Entry) if self.index() == 0 => check_key(&value)?,
+            _ => {}
+        }
+        self.focus = value;
+        Ok(self)
+    }
+
+    /// Inserts a sibling directly to the left of the current node, without
+    /// moving.
+    ///
+    /// Fails at the root, inside map entries and inside tagged literals.
+    pub fn insert_left(&mut self, value: Value) -> Result<&mut Self> {
+        self.check_resizable()?;
+        if let Some(Branch::Map) = self.parent() {
+            check_entry(&value)?;
+        }
+        let crumb = self.path.last_mut().expect("checked above");
+        crumb.left.push(value);
+        Ok(self)
+    }
+
+    /// Removes the current node, moving to the node that would have preceded
+    /// it in a depth-first walk.
+    ///
+    /// Fails at the root, inside map entries and inside tagged literals.
+    pub fn remove(&mut self) -> Result<&mut Self> {
+        self.check_resizable()?;
+        let crumb = self.path.last_mut().expect("checked above");
+        match crumb.left.pop() {
+            Some(prev) => {
+                self.focus = prev;
+                // Descend to the last node of the previous sibling.
+                while self.down().is_some() {
+                    while self.right().is_some() {}
+                }
+            }
+            None => {
+                let crumb = self.path.pop().expect("checked above");
+                let mut children = crumb.right;
+                children.reverse();
+                self.focus = build(crumb.branch, children);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Zips all the way up and returns the edited tree.
+    pub fn root(mut self) -> Value {
+        while self.up().is_some() {}
+        self.focus
+    }
+
+    fn parent(&self) -> Option<&Branch> {
+        self.path.last().map(|crumb| &crumb.branch)
+    }
+
+    fn index(&self) -> usize {
+        self.path.last().map_or(0, |crumb| crumb.left.len())
+    }
+
+    fn check_resizable(&self) -> Result<()> {
+        match self.parent() {
+            None => Err(Error::Zipper("the root has no siblings")),
+            Some(Branch::Entry) => Err(Error::Zipper("a map entry always has two children")),
+            Some(Branch::Tagged(_)) => Err(Error::Zipper("a tagged literal has a single child")),
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+fn build(branch: Branch, children: Vec<Value>) -> Value {
+    match branch {
+        Branch::Vector | Branch::Entry => Value::Vector(children.into_iter().collect()),
+        Branch::List => Value::List(children.into_iter().collect()),
+        Branch::Set => Value::Set(children.into_iter().collect()),
+        Branch::Map => Value::Map(
+            children
+                .into_iter()
+                .map(|e| split_entry(e).expect("map entries are checked on edit"))
+                .collect(),
+        ),
+        Branch::Tagged(t) => {
+            let value = children.into_iter().next().expect("tagged literal child");
+            Value::Tagged(t, Box::new(value))
+        }
+    }
+}
+
+fn entry(k: Key, v: Value) -> Value {
+    Value::Vector([k.into(), v].into_iter().collect())
+}
+
+fn split_entry(entry: Value) -> Option<(Key, Value)> {
+    match entry {
+        Value::Vector(pair) if pair.len() == 2 => {
+            let mut pair = pair.into_iter();
+            let k = pair.next()?.try_into().ok()?;
+            Some((k, pair.next()?))
+        }
+        _ => None,
+    }
+}
+
+fn check_entry(value: &Value) -> Result<()> {
+    match value {
+        Value::Vector(pair) if pair.len() == 2 => check_key(&pair[0]),
+        _ => Err(Error::Zipper("map entries must be [key value] vectors")),
+    }
+}
+
+fn check_key(value: &Value) -> Result<()> {
+    match value {
+        Value::Keyword(_)
+        | Value::String(_)
+        | Value::Symbol(_)
+        | Value::Integer(_)
+        | Value::Boolean(_)
+        | Value::Character(_)
+        | Value::Uuid(_) => Ok(()),
+        _ => Err(Error::Zipper("value can't be used as a map key")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_edn;
+
+    fn zip(s: &str) -> Zipper {
+        Zipper::new(parse_edn(s).unwrap())
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut z = zip("[1 (2 3) #foo 4]");
+        assert!(z.up().is_none());
+        assert!(z.right().is_none());
+
+        z.down().unwrap();
+        assert_eq!(z.node(), &Value::Integer(1));
+        assert!(z.left().is_none());
+        z.right().unwrap().down().unwrap().right().unwrap();
+        assert_eq!(z.node(), &Value::Integer(3));
+        assert!(z.right().is_none());
+        assert!(z.down().is_none());
+        z.up().unwrap().right().unwrap().down().unwrap();
+        assert_eq!(z.node(), &Value::Integer(4));
+        assert_eq!(z.root(), parse_edn("[1 (2 3) #foo 4]").unwrap());
+    }
+
+    #[test]
+    fn test_edit() {
+        let mut z = zip("[1 [2 3] 4]");
+        z.down().unwrap().right().unwrap().down().unwrap();
+        z.replace(Value::Integer(20)).unwrap();
+        z.insert_left(Value::Integer(10)).unwrap();
+        z.up().unwrap().right().unwrap();
+        z.remove().unwrap();
+        // The node preceding 4 in a depth-first walk is 3.
+        assert_eq!(z.node(), &Value::Integer(3));
+        assert_eq!(z.root().to_string(), "[1 [10 20 3]]");
+
+        let mut z = zip("[1 2]");
+        z.down().unwrap();
+        z.remove().unwrap();
+        assert_eq!(z.node().to_string(), "[2]");
+        assert!(z.remove().is_err());
+    }
+
+    #[test]
+    fn test_map_entries() {
+        let mut z = zip("{:a {:b 1}}");
+        z.down().unwrap();
+        assert_eq!(z.node().to_string(), "[:a {:b 1}]");
+        z.down().unwrap();
+        assert_eq!(z.node(), &Value::Keyword(":a".into()));
+        assert!(z.replace(Value::Vector(Default::default())).is_err());
+        assert!(z.remove().is_err());
+        z.replace(Value::Keyword(":x".into())).unwrap();
+        z.right()
+            .unwrap()
+            .down()
+            .unwrap()
+            .down()
+            .unwrap()
+            .right()
+            .unwrap();
+        z.replace(Value::Integer(2)).unwrap();
+        z.up().unwrap();
+        assert!(z.insert_left(Value::Integer(1)).is_err());
+        z.insert_left(parse_edn("[:c 3]").unwrap()).unwrap();
+        assert_eq!(z.root(), parse_edn("{:x {:b 2 :c 3}}").unwrap());
+    }
+}